@@ -0,0 +1,314 @@
+use crate::defaults;
+use crate::keys::Code;
+use crate::mouse;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use winit::event::VirtualKeyCode;
+
+/// What it takes for a key to trigger its action.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Mode {
+    #[default]
+    Press,
+    /// Holding the key down for the time.
+    Hold,
+    /// Pressing the key twice within the time.
+    DoubleTap,
+    /// Pressing the key and then Enter within the time. Escape cancels.
+    Confirm,
+}
+
+impl Mode {
+    /// Whether a key that is registered globally can trigger its action this
+    /// way. Global hotkeys only see the key itself being pressed.
+    pub fn works_globally(self) -> bool {
+        matches!(self, Mode::Press | Mode::DoubleTap)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Activation {
+    #[serde(default)]
+    pub mode: Mode,
+    /// In milliseconds, the mode's default if missing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    time: Option<u64>,
+    /// How long after the action the key is ignored, in milliseconds. Keeps
+    /// a bouncy switch from splitting twice.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cooldown: Option<u64>,
+}
+
+impl Activation {
+    pub const PRESS: Self = Self::new(Mode::Press, None);
+
+    pub const fn new(mode: Mode, time: Option<u64>) -> Self {
+        Self {
+            mode,
+            time,
+            cooldown: None,
+        }
+    }
+
+    /// How a key that is registered globally triggers the action. The modes
+    /// that don't work globally are replaced by a double tap, so the key
+    /// still can't be triggered by a stray press.
+    pub fn global(self) -> Self {
+        if self.mode.works_globally() {
+            return self;
+        }
+        Self {
+            mode: Mode::DoubleTap,
+            time: None,
+            ..self
+        }
+    }
+
+    /// Whether a single press triggers the action, however often.
+    pub fn is_plain(&self) -> bool {
+        self.mode == Mode::Press && self.cooldown.is_none()
+    }
+
+    pub fn cooldown(&self) -> Option<Duration> {
+        self.cooldown.map(Duration::from_millis)
+    }
+
+    pub fn time(&self) -> Duration {
+        Duration::from_millis(self.time.unwrap_or(match self.mode {
+            Mode::Press => 0,
+            Mode::Hold => defaults::HOLD_MS,
+            Mode::DoubleTap => defaults::DOUBLE_TAP_MS,
+            Mode::Confirm => defaults::CONFIRM_MS,
+        }))
+    }
+}
+
+/// What is pressed and, for `Mode::Hold`, let go of.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Input {
+    Key(Code),
+    /// The wheel is never let go of, it can't be held.
+    Mouse(mouse::Input),
+    /// A key registered globally, which is only ever seen being pressed.
+    Global,
+}
+
+/// An action waiting for its activation to be completed.
+pub struct Pending<T> {
+    pub action: T,
+    pub activation: Activation,
+    input: Input,
+    since: Instant,
+}
+
+/// Tracks the action that is being activated. Only one can be at a time,
+/// pressing another key or mouse button cancels it.
+pub struct Activator<T> {
+    pending: Option<Pending<T>>,
+}
+
+impl<T: Copy + PartialEq + std::fmt::Debug> Activator<T> {
+    pub fn new() -> Self {
+        Self { pending: None }
+    }
+
+    pub fn pending(&self) -> Option<&Pending<T>> {
+        self.pending.as_ref()
+    }
+
+    pub fn cancel(&mut self) {
+        self.pending = None;
+    }
+
+    /// Handles a key or mouse button press, bound to `binding` if it is
+    /// bound at all. Returns the action to run right away.
+    pub fn press(&mut self, input: Input, binding: Option<(T, Activation)>) -> Option<T> {
+        if let Some(pending) = self.pending.take() {
+            match pending.activation.mode {
+                Mode::Confirm
+                    if matches!(
+                        input,
+                        Input::Key(Code::Virtual(
+                            VirtualKeyCode::Return | VirtualKeyCode::NumpadEnter
+                        ))
+                    ) =>
+                {
+                    return Some(pending.action);
+                }
+                Mode::Confirm if input == Input::Key(Code::Virtual(VirtualKeyCode::Escape)) => {
+                    log::warn!("Cancelled {:?}", pending.action);
+                    return None;
+                }
+                Mode::DoubleTap if binding.map(|(action, _)| action) == Some(pending.action) => {
+                    return Some(pending.action);
+                }
+                _ => log::warn!("Cancelled {:?}, another key was pressed", pending.action),
+            }
+        }
+        let (action, activation) = binding?;
+        if activation.mode == Mode::Press {
+            return Some(action);
+        }
+        self.pending = Some(Pending {
+            action,
+            activation,
+            input,
+            since: Instant::now(),
+        });
+        None
+    }
+
+    /// Handles a key or mouse button being let go of. Holding stops counting
+    /// once it is released, even if the modifiers are still held.
+    pub fn release(&mut self, input: Input) {
+        if let Some(pending) = &self.pending {
+            if pending.activation.mode == Mode::Hold && pending.input == input {
+                log::warn!("Cancelled {:?}, released too early", pending.action);
+                self.pending = None;
+            }
+        }
+    }
+
+    /// Returns the action once it has been held long enough, and gives up on
+    /// the others when their time is up.
+    pub fn poll(&mut self) -> Option<T> {
+        let pending = self.pending.as_ref()?;
+        if pending.since.elapsed() < pending.activation.time() {
+            return None;
+        }
+        let pending = self.pending.take()?;
+        if pending.activation.mode == Mode::Hold {
+            return Some(pending.action);
+        }
+        log::warn!("Cancelled {:?}, timed out", pending.action);
+        None
+    }
+
+    /// How far along a hold is, or how much time is left for the second tap
+    /// or the confirmation, from 0 to 1.
+    pub fn progress(&self) -> Option<f32> {
+        let pending = self.pending.as_ref()?;
+        let time = pending.activation.time().as_secs_f32();
+        let elapsed = (pending.since.elapsed().as_secs_f32() / time).min(1.0);
+        Some(match pending.activation.mode {
+            Mode::Hold => elapsed,
+            _ => 1.0 - elapsed,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(key: VirtualKeyCode) -> Input {
+        Input::Key(Code::Virtual(key))
+    }
+
+    fn bound(mode: Mode, time: Option<u64>) -> Option<(&'static str, Activation)> {
+        Some(("reset", Activation::new(mode, time)))
+    }
+
+    #[test]
+    fn a_press_triggers_right_away() {
+        let mut activator = Activator::new();
+        let press = bound(Mode::Press, None);
+        assert_eq!(
+            activator.press(key(VirtualKeyCode::A), press),
+            Some("reset")
+        );
+        assert!(activator.pending().is_none());
+    }
+
+    #[test]
+    fn holding_triggers_once_the_time_is_up() {
+        let mut activator = Activator::new();
+        assert_eq!(
+            activator.press(key(VirtualKeyCode::A), bound(Mode::Hold, Some(0))),
+            None
+        );
+        assert_eq!(activator.poll(), Some("reset"));
+        assert_eq!(activator.poll(), None);
+    }
+
+    #[test]
+    fn releasing_too_early_cancels_the_hold() {
+        let mut activator = Activator::new();
+        let hold = bound(Mode::Hold, Some(60_000));
+        activator.press(key(VirtualKeyCode::A), hold);
+        assert_eq!(activator.poll(), None);
+        // Letting go of another key doesn't count.
+        activator.release(key(VirtualKeyCode::B));
+        assert!(activator.pending().is_some());
+        activator.release(key(VirtualKeyCode::A));
+        assert!(activator.pending().is_none());
+    }
+
+    #[test]
+    fn a_second_tap_triggers() {
+        let mut activator = Activator::new();
+        let tap = bound(Mode::DoubleTap, Some(60_000));
+        assert_eq!(activator.press(key(VirtualKeyCode::A), tap), None);
+        assert_eq!(activator.press(key(VirtualKeyCode::A), tap), Some("reset"));
+        assert!(activator.pending().is_none());
+    }
+
+    #[test]
+    fn a_late_or_other_key_cancels_the_double_tap() {
+        let mut activator = Activator::new();
+        let tap = bound(Mode::DoubleTap, Some(60_000));
+        activator.press(key(VirtualKeyCode::A), tap);
+        assert_eq!(activator.press(key(VirtualKeyCode::B), None), None);
+        assert_eq!(activator.press(key(VirtualKeyCode::A), tap), None);
+
+        let late = bound(Mode::DoubleTap, Some(0));
+        let mut activator = Activator::new();
+        activator.press(key(VirtualKeyCode::A), late);
+        assert_eq!(activator.poll(), None);
+        assert!(activator.pending().is_none());
+    }
+
+    #[test]
+    fn enter_confirms_and_escape_cancels() {
+        let mut activator = Activator::new();
+        let confirm = bound(Mode::Confirm, Some(60_000));
+        activator.press(key(VirtualKeyCode::A), confirm);
+        assert_eq!(
+            activator.press(key(VirtualKeyCode::Return), None),
+            Some("reset")
+        );
+
+        activator.press(key(VirtualKeyCode::A), confirm);
+        assert_eq!(activator.press(key(VirtualKeyCode::Escape), None), None);
+        assert!(activator.pending().is_none());
+        assert_eq!(activator.press(key(VirtualKeyCode::Return), None), None);
+    }
+
+    #[test]
+    fn losing_the_focus_cancels() {
+        let mut activator = Activator::new();
+        activator.press(key(VirtualKeyCode::A), bound(Mode::Hold, Some(0)));
+        activator.cancel();
+        assert_eq!(activator.poll(), None);
+
+        activator.press(key(VirtualKeyCode::A), bound(Mode::Confirm, None));
+        activator.cancel();
+        assert_eq!(activator.press(key(VirtualKeyCode::Return), None), None);
+    }
+
+    #[test]
+    fn global_keys_take_a_double_tap_instead_of_a_hold_or_confirmation() {
+        let hold = Activation::new(Mode::Hold, Some(800));
+        assert_eq!(hold.global(), Activation::new(Mode::DoubleTap, None));
+        let confirm = Activation::new(Mode::Confirm, None);
+        assert_eq!(confirm.global().mode, Mode::DoubleTap);
+        assert_eq!(Activation::PRESS.global(), Activation::PRESS);
+
+        let mut activator = Activator::new();
+        let global = Some(("reset", hold.global()));
+        assert_eq!(activator.press(Input::Global, global), None);
+        assert_eq!(activator.press(Input::Global, global), Some("reset"));
+    }
+}
@@ -0,0 +1,193 @@
+use crate::defaults;
+use crate::keys::{Binding, Code, Key};
+use winit::event::VirtualKeyCode;
+
+/// Every key setting, the timer hotkeys first.
+pub fn names() -> Vec<String> {
+    let hotkeys = defaults::HOTKEYS.iter().map(|(name, _)| name.to_string());
+    let keys = defaults::keys().into_iter().map(|(name, _)| name);
+    hotkeys.chain(keys).collect()
+}
+
+/// What a key pressed while binding amounts to.
+pub enum Outcome {
+    Continue,
+    Done,
+    /// Bind the setting with the name to the key.
+    Bind(String, Binding),
+}
+
+/// Picking a key setting with the arrow keys and pressing the keys to bind it
+/// to, see `Action::Bind`.
+pub struct Binder {
+    names: Vec<String>,
+    selected: usize,
+    capturing: bool,
+    /// Why the last key pressed wasn't bound.
+    problem: Option<String>,
+}
+
+impl Binder {
+    pub fn new() -> Self {
+        Self {
+            names: names(),
+            selected: 0,
+            capturing: false,
+            problem: None,
+        }
+    }
+
+    pub fn selected(&self) -> &str {
+        &self.names[self.selected]
+    }
+
+    /// Handles a key press. `scancode` is the key's, for keys that have no
+    /// name. `bound_to` tells which setting a key is bound to already.
+    pub fn press(
+        &mut self,
+        key: Key,
+        scancode: u32,
+        bound_to: impl Fn(&Key) -> Option<String>,
+    ) -> Outcome {
+        if key.is_modifier() {
+            return Outcome::Continue;
+        }
+        let plain = |code: VirtualKeyCode| key == Key::new(code);
+        if !self.capturing {
+            if plain(VirtualKeyCode::Up) {
+                self.selected = (self.selected + self.names.len() - 1) % self.names.len();
+            } else if plain(VirtualKeyCode::Down) {
+                self.selected = (self.selected + 1) % self.names.len();
+            } else if plain(VirtualKeyCode::Return) || plain(VirtualKeyCode::NumpadEnter) {
+                self.capturing = true;
+            } else if plain(VirtualKeyCode::Escape) {
+                return Outcome::Done;
+            }
+            return Outcome::Continue;
+        }
+        if plain(VirtualKeyCode::Escape) {
+            self.capturing = false;
+            self.problem = None;
+            return Outcome::Continue;
+        }
+        let binding = match key.key {
+            Code::Scan(_) => Binding::Scan(key),
+            // Keys livesplit-core has no name for are bound by scancode.
+            Code::Virtual(_) => key
+                .to_string()
+                .parse()
+                .unwrap_or_else(|_| Binding::Scan(Key::scan(scancode, key.mods))),
+        };
+        match bound_to(&key) {
+            Some(other) if other != self.selected() => {
+                self.problem = Some(format!("{} is bound to `{}`", binding, other));
+                Outcome::Continue
+            }
+            _ => {
+                self.capturing = false;
+                self.problem = None;
+                Outcome::Bind(self.selected().to_owned(), binding)
+            }
+        }
+    }
+
+    /// What to do next, `current` being the key the selected setting is
+    /// bound to.
+    pub fn prompt(&self, current: Option<Binding>) -> String {
+        let name = self.selected();
+        match (&self.problem, self.capturing) {
+            (Some(problem), _) => format!("{}, press another key for `{}`", problem, name),
+            (None, true) => format!("Press the keys for `{}`, Escape to go back", name),
+            (None, false) => {
+                let current = current.map_or_else(|| "unbound".to_owned(), |b| b.to_string());
+                format!(
+                    "Bind `{}` ({}): Up/Down to pick, Enter to bind, Escape when done",
+                    name, current
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(binder: &mut Binder, code: VirtualKeyCode) -> Outcome {
+        binder.press(Key::new(code), 0, |_| None)
+    }
+
+    #[test]
+    fn picking_wraps_around() {
+        let mut binder = Binder::new();
+        let first = binder.selected().to_owned();
+        press(&mut binder, VirtualKeyCode::Up);
+        assert_eq!(binder.selected(), names().last().unwrap());
+        press(&mut binder, VirtualKeyCode::Down);
+        assert_eq!(binder.selected(), first);
+    }
+
+    #[test]
+    fn binds_the_key_pressed_after_enter() {
+        let mut binder = Binder::new();
+        press(&mut binder, VirtualKeyCode::Down);
+        assert!(matches!(
+            press(&mut binder, VirtualKeyCode::Return),
+            Outcome::Continue
+        ));
+        let selected = binder.selected().to_owned();
+        let key = Key::ctrl(VirtualKeyCode::A);
+        match binder.press(key, 0, |_| None) {
+            Outcome::Bind(name, binding) => {
+                assert_eq!(name, selected);
+                assert_eq!(binding, "Ctrl+KeyA".parse().unwrap());
+            }
+            _ => panic!("`{}` wasn't bound", key),
+        }
+        // Back to picking.
+        press(&mut binder, VirtualKeyCode::Down);
+        assert_ne!(binder.selected(), selected);
+    }
+
+    #[test]
+    fn modifiers_alone_are_not_bound() {
+        let mut binder = Binder::new();
+        press(&mut binder, VirtualKeyCode::Return);
+        assert!(matches!(
+            press(&mut binder, VirtualKeyCode::LControl),
+            Outcome::Continue
+        ));
+    }
+
+    #[test]
+    fn keys_bound_to_other_settings_are_refused() {
+        let mut binder = Binder::new();
+        let selected = binder.selected().to_owned();
+        press(&mut binder, VirtualKeyCode::Return);
+        let key = Key::new(VirtualKeyCode::A);
+        let outcome = binder.press(key, 0, |_| Some("quit".to_owned()));
+        assert!(matches!(outcome, Outcome::Continue));
+        assert!(binder.prompt(None).starts_with("KeyA is bound to `quit`"));
+
+        // A key already bound to the selected setting is fine.
+        let outcome = binder.press(key, 0, |_| Some(selected.clone()));
+        assert!(matches!(outcome, Outcome::Bind(name, _) if name == selected));
+        assert!(!binder.prompt(None).contains("is bound to"));
+    }
+
+    #[test]
+    fn escape_stops_capturing_and_then_binding() {
+        let mut binder = Binder::new();
+        press(&mut binder, VirtualKeyCode::Return);
+        binder.press(Key::new(VirtualKeyCode::A), 0, |_| Some("quit".to_owned()));
+        assert!(matches!(
+            press(&mut binder, VirtualKeyCode::Escape),
+            Outcome::Continue
+        ));
+        assert!(binder.prompt(None).starts_with("Bind "));
+        assert!(matches!(
+            press(&mut binder, VirtualKeyCode::Escape),
+            Outcome::Done
+        ));
+    }
+}
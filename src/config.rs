@@ -1,4 +1,14 @@
+use crate::activation::{Activation, Mode};
+use crate::defaults::{self, MAX_RECENT};
+use crate::keys::{self, Binding};
+use crate::migrate;
+use crate::mouse::{self, Mouse};
+use crate::overrides::{self, Override};
+use crate::paths::{self, Paths};
+use crate::persist;
+use crate::sidecar::SplitsSettings;
 use crate::stream_markers;
+use lazy_static::lazy_static;
 use livesplit_core::hotkey::Hotkey;
 use livesplit_core::{
     auto_splitting,
@@ -6,35 +16,205 @@ use livesplit_core::{
     run::{parser::composite, saver::livesplit::save_timer},
     HotkeyConfig, HotkeySystem, Run, Segment, SharedTimer, Timer, TimingMethod,
 };
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize, Serializer};
+use serde_yaml::{Mapping, Value};
+use std::collections::{BTreeMap, HashMap};
 use std::{
     fmt, fs,
-    io::Cursor,
+    io::{self, Cursor},
+    ops::Range,
     path::{Path, PathBuf},
-    time::SystemTime,
+    sync::{Mutex, RwLock},
+    thread,
+    time::{Duration, SystemTime},
 };
 
+lazy_static! {
+    /// The prefs file to use instead of the default one.
+    static ref CONFIG_FILE: RwLock<Option<PathBuf>> = RwLock::new(None);
+    /// The settings layered over the prefs file whenever it is read.
+    static ref OVERRIDES: RwLock<Vec<Override>> = RwLock::new(Vec::new());
+    /// What the app wrote to the prefs file that the watcher hasn't seen yet,
+    /// oldest first, so it can tell the app's own writes from edits.
+    static ref WRITTEN: Mutex<Vec<Vec<u8>>> = Mutex::new(Vec::new());
+}
+
 #[derive(Default, Deserialize, Serialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct Config {
+    /// The version of the file format, see `migrate`.
+    #[serde(default)]
+    version: u64,
     #[serde(default)]
     general: General,
     log: Option<Log>,
     #[serde(default)]
     window: Window,
     #[serde(default)]
-    hotkeys: HotkeyConfig,
+    hotkeys: HashMap<String, Binding>,
     #[serde(default)]
     connections: Connections,
     #[serde(default)]
-    keys: HashMap<String, Hotkey>,
+    keys: HashMap<String, Binding>,
+    #[serde(default)]
+    chords: Chords,
+    /// How the keys in `hotkeys` and `keys` trigger their action, by the name
+    /// of the key.
+    #[serde(default)]
+    activation: BTreeMap<String, Activation>,
+    /// Mouse buttons and wheel directions like `Ctrl+Middle`, bound to the
+    /// name of a setting in `hotkeys` or `keys`. Nothing unbinds a default.
+    #[serde(default)]
+    mouse: BTreeMap<String, Option<String>>,
+    /// Shell commands run on timer changes, by the timer action like
+    /// `Split` or `Reset`.
+    #[serde(default)]
+    hooks: BTreeMap<String, String>,
+    #[serde(default)]
+    recent: Recent,
+    #[serde(default)]
+    paths: Paths,
+    /// The profile used when none is picked on the command line.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    profile: Option<String>,
+    /// Overrides of the `general`, `window`, `hotkeys`, `keys` and
+    /// `connections` sections, by profile name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    profiles: BTreeMap<String, Value>,
+    #[serde(skip)]
+    active_profile: Option<String>,
+    /// How the active profile was picked, to pick it the same way again.
+    #[serde(skip)]
+    selected_profile: Profile,
+    /// The settings of the loaded splits file, which take precedence over
+    /// the ones in `general`.
+    #[serde(skip)]
+    splits_settings: Option<SplitsSettings>,
+    /// The settings as they were last read or written. Only what changed
+    /// since then is saved, on top of the current contents of the file.
+    #[serde(skip)]
+    saved: Option<Value>,
+    /// The prefs file as it was last read or written, to tell where a
+    /// setting comes from.
+    #[serde(skip)]
+    file: Option<Value>,
+    /// Set when the prefs file exists but couldn't be parsed, so that the
+    /// defaults used instead are never saved over it.
+    #[serde(skip)]
+    locked: bool,
 }
-fn default_state_file() -> PathBuf {
-    PathBuf::from("livesplit_state.lsz")
+
+/// The sections a profile can override.
+const PROFILE_SECTIONS: [&str; 10] = [
+    "general",
+    "window",
+    "hotkeys",
+    "keys",
+    "chords",
+    "activation",
+    "mouse",
+    "connections",
+    "hooks",
+    "recent",
+];
+
+/// Which profile to apply over the base settings.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub enum Profile {
+    /// The one the prefs file picks in `profile`, if any.
+    #[default]
+    Default,
+    /// Just the base settings, even if the prefs file picks a profile.
+    Base,
+    Named(String),
 }
 
-#[derive(Default, Deserialize, Serialize)]
+impl From<Option<&str>> for Profile {
+    /// The profile picked on the command line, or the default one.
+    fn from(name: Option<&str>) -> Self {
+        name.map_or(Profile::Default, |name| Profile::Named(name.to_owned()))
+    }
+}
+
+/// The result of checking a prefs file. `config` is only set if there were no
+/// errors.
+pub struct Check {
+    pub config: Option<Config>,
+    pub errors: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+impl Check {
+    pub fn report(&self) {
+        for error in &self.errors {
+            log::error!("Preferences: {}", error);
+        }
+        for warning in &self.warnings {
+            log::warn!("Preferences: {}", warning);
+        }
+    }
+}
+
+/// Where the value of a setting comes from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Origin {
+    Default,
+    File,
+    Profile(String),
+    /// The settings stored next to the splits file.
+    SplitsFile,
+    Override(overrides::Source),
+    /// Changed in the app since the prefs were read.
+    Runtime,
+    /// Named after the loaded splits file.
+    Splits,
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Origin::Default => f.write_str("default"),
+            Origin::File => f.write_str("prefs file"),
+            Origin::Profile(name) => write!(f, "profile `{}`", name),
+            Origin::SplitsFile => f.write_str("splits settings"),
+            Origin::Override(source) => source.fmt(f),
+            Origin::Runtime => f.write_str("set while running"),
+            Origin::Splits => f.write_str("named after the splits file"),
+        }
+    }
+}
+
+impl Serialize for Origin {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// A setting in effect, as shown by `--print-config`.
+#[derive(Serialize)]
+pub struct Setting {
+    pub path: String,
+    pub value: Value,
+    pub source: Origin,
+}
+
+impl fmt::Display for Setting {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = serde_json::to_string(&self.value).unwrap_or_default();
+        write!(f, "{} = {} ({})", self.path, value, self.source)
+    }
+}
+
+/// The parts of the config that differ between two versions of it.
+pub struct Changes {
+    pub splits: bool,
+    pub layout: bool,
+    pub hotkeys: bool,
+    pub timer: bool,
+    pub connections: bool,
+}
+
+#[derive(Default, Deserialize, Serialize, PartialEq)]
 #[serde(rename_all = "kebab-case")]
 struct General {
     splits: Option<PathBuf>,
@@ -43,8 +223,12 @@ struct General {
     comparison: Option<String>,
     auto_splitter: Option<PathBuf>,
     use_global_hotkeys: Option<bool>,
-    #[serde(default = "default_state_file")]
-    state_file: PathBuf,
+    /// The settings in `keys` that are registered globally too when global
+    /// hotkeys are on. Only the timer's `hotkeys` are by default.
+    #[serde(default)]
+    global_keys: Vec<String>,
+    /// Relative to the state directory.
+    state_file: Option<PathBuf>,
 }
 
 #[derive(Default, Deserialize, Serialize)]
@@ -67,21 +251,61 @@ struct Window {
     fps: f32,
 }
 
-#[derive(Default, Deserialize, Serialize)]
+/// Key sequences like `Ctrl+K R`, bound to the name of a setting in the
+/// `hotkeys` or `keys` section. They only work in the window.
+#[derive(Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+#[serde(default)]
+struct Chords {
+    /// How long to wait for the next key of a chord, in milliseconds.
+    timeout: u64,
+    bindings: BTreeMap<String, String>,
+}
+
+impl Default for Chords {
+    fn default() -> Self {
+        Self {
+            timeout: defaults::CHORD_TIMEOUT_MS,
+            bindings: BTreeMap::new(),
+        }
+    }
+}
+
+/// The most recently used files first, and the directories the file dialogs
+/// were last in.
+#[derive(Default, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+#[serde(default)]
+struct Recent {
+    splits: Vec<PathBuf>,
+    layouts: Vec<PathBuf>,
+    splits_dir: Option<PathBuf>,
+    layout_dir: Option<PathBuf>,
+}
+
+fn push_recent(list: &mut Vec<PathBuf>, path: &Path) {
+    list.retain(|p| p != path);
+    list.insert(0, path.to_path_buf());
+    list.truncate(MAX_RECENT);
+}
+
+#[derive(Default, Deserialize, Serialize, PartialEq)]
 #[serde(rename_all = "kebab-case")]
 #[serde(default)]
 struct Connections {
     twitch: Option<String>,
+    marker_file: Option<PathBuf>,
+    webhooks: Vec<String>,
 }
 
 impl Default for Window {
     fn default() -> Window {
         Self {
-            width: 300,
-            height: 500,
-            always_on_top: false,
-            transparency: true,
-            fps: 60.0,
+            width: defaults::WINDOW_WIDTH,
+            height: defaults::WINDOW_HEIGHT,
+            always_on_top: defaults::ALWAYS_ON_TOP,
+            transparency: defaults::TRANSPARENCY,
+            fps: defaults::FPS,
         }
     }
 }
@@ -99,6 +323,9 @@ impl Config {
         }
     }
     pub fn config_file() -> PathBuf {
+        if let Some(path) = &*CONFIG_FILE.read().unwrap() {
+            return path.clone();
+        }
         if let Some(base_dirs) = directories::BaseDirs::new() {
             let mut path = base_dirs.preference_dir().to_path_buf();
             path.push("livesplit_one_prefs.yaml");
@@ -110,22 +337,596 @@ impl Config {
             path
         }
     }
-    pub fn save_config(&self) {
-        let data = serde_yaml::to_string(self).unwrap();
-        fs::write(Self::config_file(), data).unwrap();
+    pub fn set_config_file(path: PathBuf) {
+        *CONFIG_FILE.write().unwrap() = Some(path);
+    }
+    /// Sets the overrides applied every time the prefs file is read. Later
+    /// overrides win over earlier ones.
+    pub fn set_overrides(overrides: Vec<Override>) {
+        *OVERRIDES.write().unwrap() = overrides;
+    }
+    /// Stops applying the overrides of settings that were changed in the app
+    /// since, like a splits file opened from the menu, so reading the prefs
+    /// file again doesn't undo the change.
+    pub fn drop_stale_overrides(&self) {
+        // Read back as settings, so `40` and `40.0` are the same.
+        let settings = |value| {
+            let config = serde_yaml::from_value::<Config>(value).ok()?;
+            serde_yaml::to_value(config).ok()
+        };
+        let schema = schema();
+        let current = serde_yaml::to_value(self).unwrap();
+        let normalized = settings(current.clone());
+        OVERRIDES.write().unwrap().retain(|o| {
+            let mut value = current.clone();
+            o.apply(&mut value, &schema);
+            match (settings(value), &normalized) {
+                (Some(value), Some(current)) if value != *current => {
+                    log::info!("Not applying the {} anymore, it was changed", o);
+                    false
+                }
+                _ => true,
+            }
+        });
+    }
+    /// Saves the settings changed in the app, on top of any edits made to the
+    /// file on disk. While a profile is active, the changes go into its
+    /// overrides. Returns false if the file failed to parse, which it is
+    /// never saved over.
+    pub fn save_config(&mut self) -> bool {
+        if self.locked {
+            log::warn!(
+                "Not saving the preferences, {} failed to parse",
+                Self::config_file().display()
+            );
+            return false;
+        }
+        self.version = migrate::VERSION;
+        let ours = serde_yaml::to_value(&*self).unwrap();
+        let theirs = persist::read(&Self::config_file())
+            .ok()
+            .and_then(|buf| serde_yaml::from_slice::<Value>(&buf).ok());
+        let merged = match (&self.saved, theirs) {
+            (Some(base), Some(mut theirs)) => {
+                match &self.active_profile {
+                    Some(name) => merge(base, &ours, profile_mut(&mut theirs, name)),
+                    None => merge(base, &ours, &mut theirs),
+                }
+                theirs
+            }
+            _ => ours.clone(),
+        };
+        let data = serde_yaml::to_string(&merged).unwrap();
+        WRITTEN.lock().unwrap().push(data.clone().into_bytes());
+        persist::save(&Self::config_file(), data, Some(self.paths.backups()));
+        self.saved = Some(ours);
+        self.file = Some(merged);
+        true
+    }
+
+    /// Calls `on_change` from a background thread whenever the prefs file is
+    /// edited. Writes by the app itself are ignored.
+    pub fn watch(on_change: impl Fn() + Send + 'static) {
+        let path = Self::config_file();
+        let modified = |path: &Path| fs::metadata(path).and_then(|m| m.modified()).ok();
+        thread::Builder::new()
+            .name("config watcher".into())
+            .spawn(move || {
+                let mut last = modified(&path);
+                loop {
+                    thread::sleep(Duration::from_secs(1));
+                    let current = modified(&path);
+                    if current == last {
+                        continue;
+                    }
+                    last = current;
+                    let contents = fs::read(&path).unwrap_or_default();
+                    let mut written = WRITTEN.lock().unwrap();
+                    match written.iter().position(|data| *data == contents) {
+                        Some(n) => {
+                            written.drain(..=n);
+                            log::debug!("Ignored the app's own write to {}", path.display());
+                        }
+                        None => {
+                            drop(written);
+                            on_change();
+                        }
+                    }
+                }
+            })
+            .unwrap();
+    }
+
+    pub fn changes(&self, new: &Config) -> Changes {
+        let (old, new_general) = (&self.general, &new.general);
+        Changes {
+            splits: old.splits != new_general.splits || self.auto_splitter() != new.auto_splitter(),
+            layout: self.layout_path() != new.layout_path(),
+            hotkeys: self.hotkeys != new.hotkeys
+                || self.keys != new.keys
+                || self.chords != new.chords
+                || self.activation != new.activation
+                || self.mouse != new.mouse
+                || old.use_global_hotkeys != new_general.use_global_hotkeys
+                || old.global_keys != new_general.global_keys,
+            timer: self.comparison() != new.comparison()
+                || self.timing_method() != new.timing_method(),
+            connections: self.connections != new.connections,
+        }
     }
+    /// The state file of the loaded splits file, or the one in `general` if
+    /// there is none. Either is in the state directory.
     pub fn state_file(&self) -> PathBuf {
-        self.general.state_file.clone()
+        let dir = self.paths.state_dir();
+        match &self.general.splits {
+            Some(splits) => dir.join(splits_state_file(splits)),
+            None => {
+                let name = self.general.state_file.as_deref();
+                dir.join(name.unwrap_or_else(|| Path::new(defaults::STATE_FILE)))
+            }
+        }
+    }
+    /// Where older builds kept the state file: next to the splits file, or
+    /// in the working directory.
+    pub fn legacy_state_file(&self) -> PathBuf {
+        match &self.general.splits {
+            Some(splits) => splits.with_extension("lsz"),
+            None => PathBuf::from(defaults::STATE_FILE),
+        }
+    }
+    pub fn paths(&self) -> &Paths {
+        &self.paths
+    }
+    pub fn active_profile(&self) -> Option<&str> {
+        self.active_profile.as_deref()
     }
-    pub fn set_state_file(&mut self, path: &Path) {
-        self.general.state_file = path.to_path_buf();
+    /// How the active profile was picked, for reading the prefs file again.
+    pub fn selected_profile(&self) -> Profile {
+        self.selected_profile.clone()
     }
-    pub fn parse() -> Option<Config> {
-        Self::parse_config(Self::config_file())
+    /// The profile after the active one, or the base settings after the last
+    /// one.
+    pub fn next_profile(&self) -> Profile {
+        let mut names = self.profiles.keys();
+        let next = match &self.active_profile {
+            Some(active) => names.skip_while(|name| *name != active).nth(1),
+            None => names.next(),
+        };
+        next.cloned().map_or(Profile::Base, Profile::Named)
     }
-    pub fn parse_config(path: impl AsRef<Path>) -> Option<Config> {
-        let buf = fs::read(path).ok()?;
-        serde_yaml::from_slice(&buf).ok()
+    /// Reads the prefs file, falling back to the defaults if there is none.
+    /// If the file exists but can't be parsed, the defaults are locked so the
+    /// file is never overwritten. The check should be reported once logging
+    /// is set up.
+    pub fn load(profile: &Profile) -> (Config, Option<Check>) {
+        let upgraded = Self::upgrade();
+        match Self::check(Self::config_file(), profile) {
+            Ok(mut check) => {
+                check.warnings.extend(upgraded);
+                let config = check.config.take().unwrap_or_else(|| Config {
+                    locked: true,
+                    ..Default::default()
+                });
+                (config, Some(check))
+            }
+            Err(_) => (Config::default(), None),
+        }
+    }
+    pub fn parse(profile: &Profile) -> Option<Config> {
+        let upgraded = Self::upgrade();
+        let mut check = Self::check(Self::config_file(), profile).ok()?;
+        check.warnings.extend(upgraded);
+        check.report();
+        check.config.take()
+    }
+    /// Upgrades a prefs file written by an older build in place, keeping a
+    /// copy of the original in the backup directory. Files that can't be
+    /// read, parsed or are from a newer build are left alone for `check` to
+    /// report.
+    fn upgrade() -> Option<String> {
+        let path = Self::config_file();
+        let mut value = serde_yaml::from_slice::<Value>(&persist::read(&path).ok()?).ok()?;
+        let from = migrate::version(&value).ok()?;
+        if from >= migrate::VERSION {
+            return None;
+        }
+        migrate::migrate(&mut value).ok()?;
+
+        let dir = value
+            .get("paths")
+            .and_then(|paths| serde_yaml::from_value::<Paths>(paths.clone()).ok())
+            .unwrap_or_default()
+            .backup_dir();
+        let mut backup = path.file_name()?.to_os_string();
+        backup.push(format!(".v{}.bak", from));
+        let backup = dir.join(backup);
+        let data = serde_yaml::to_string(&value).unwrap();
+        let result = fs::create_dir_all(&dir)
+            .and_then(|_| fs::copy(&path, &backup))
+            .and_then(|_| persist::write(&path, data.as_bytes(), None));
+        Some(match result {
+            Ok(()) => format!(
+                "upgraded from version {} to {}, the old file is kept at {}",
+                from,
+                migrate::VERSION,
+                backup.display()
+            ),
+            Err(e) => format!(
+                "couldn't upgrade from version {} to {}: {}",
+                from,
+                migrate::VERSION,
+                e
+            ),
+        })
+    }
+    /// Checks the prefs file, with the overrides of `profile` applied.
+    pub fn check(path: impl AsRef<Path>, profile: &Profile) -> io::Result<Check> {
+        let text = fs::read_to_string(path)?;
+        let mut check = Check {
+            config: None,
+            errors: Vec::new(),
+            warnings: Vec::new(),
+        };
+
+        let mut value = match serde_yaml::from_str::<Value>(&text) {
+            Ok(value) => value,
+            Err(e) => {
+                check.errors.push(e.to_string());
+                return Ok(check);
+            }
+        };
+        let from = match migrate::migrate(&mut value) {
+            Ok(from) => from,
+            Err(e) => {
+                check.errors.push(e);
+                return Ok(check);
+            }
+        };
+        if from < migrate::VERSION {
+            check.warnings.push(format!(
+                "the preferences are version {}, they will be upgraded to version {}",
+                from,
+                migrate::VERSION
+            ));
+        }
+
+        // Deserialize every top level section on its own, with all the other
+        // lines blanked out, so that an error in one doesn't hide the errors
+        // in the others while keeping the line numbers intact. The errors
+        // point into the file as it is written, only a section the upgrade
+        // changes is checked as upgraded, by its first line.
+        let lines: Vec<&str> = text.lines().collect();
+        for range in sections(&lines) {
+            let section: Vec<&str> = lines
+                .iter()
+                .enumerate()
+                .map(|(i, line)| if range.contains(&i) { *line } else { "" })
+                .collect();
+            let section = section.join("\n");
+            let error = match upgraded_section(&section, from) {
+                Some(upgraded) => serde_yaml::from_value::<Config>(upgraded)
+                    .err()
+                    .map(|e| format!("{} (line {}, as upgraded)", e, range.start + 1)),
+                None => serde_yaml::from_str::<Config>(&section)
+                    .err()
+                    .map(|e| e.to_string()),
+            };
+            check.errors.extend(error);
+        }
+        let config = match serde_yaml::from_value::<Config>(value.clone()) {
+            Ok(config) => config,
+            Err(e) => {
+                if check.errors.is_empty() {
+                    check.errors.push(e.to_string());
+                }
+                return Ok(check);
+            }
+        };
+        if !check.errors.is_empty() {
+            return Ok(check);
+        }
+
+        let schema = schema();
+        unknown_keys(&schema, &value, "", &mut check.warnings);
+        for (name, profile) in &config.profiles {
+            let path = format!("profiles.{}", name);
+            if let Value::Mapping(sections) = profile {
+                for section in sections.keys() {
+                    let known = section
+                        .as_str()
+                        .is_some_and(|s| PROFILE_SECTIONS.contains(&s));
+                    if !known {
+                        check.warnings.push(format!(
+                            "`{}` can't override {:?}, only {}",
+                            path,
+                            section,
+                            PROFILE_SECTIONS.join(", ")
+                        ));
+                    }
+                }
+            }
+            unknown_keys(&schema, profile, &path, &mut check.warnings);
+        }
+
+        let overrides = OVERRIDES.read().unwrap();
+        for o in overrides.iter() {
+            if !o.is_known(&schema) {
+                check.warnings.push(format!("unknown setting in the {}", o));
+            }
+        }
+
+        let active = match profile {
+            Profile::Default => config.profile.clone(),
+            Profile::Base => None,
+            Profile::Named(name) => Some(name.clone()),
+        };
+        let config = if active.is_some() || !overrides.is_empty() {
+            match effective(&value, active.as_deref(), &overrides, &schema) {
+                Ok(config) => config,
+                Err(e) => {
+                    check.errors.push(e);
+                    return Ok(check);
+                }
+            }
+        } else {
+            config
+        };
+
+        for name in config.hotkeys.keys() {
+            if defaults::hotkey(name).is_none() {
+                let names: Vec<&str> = defaults::HOTKEYS.iter().map(|(name, _)| *name).collect();
+                check.warnings.push(format!(
+                    "unknown hotkey `hotkeys.{}`, expected one of {}",
+                    name,
+                    names.join(", ")
+                ));
+            }
+        }
+        for name in config.keys.keys() {
+            if defaults::key(name).is_none() {
+                let names: Vec<&str> = defaults::KEYS.iter().map(|(name, _)| *name).collect();
+                check.warnings.push(format!(
+                    "unknown key binding `keys.{}`, expected one of {}, recent_splits_<n> or recent_layout_<n>",
+                    name,
+                    names.join(", ")
+                ));
+            }
+        }
+        for name in &config.general.global_keys {
+            if defaults::key(name).is_none() {
+                check.warnings.push(format!(
+                    "`general.global-keys` lists `{}`, which is not in `keys`",
+                    name
+                ));
+            }
+        }
+        let bindings = config.effective_bindings();
+        for (sequence, name) in &config.chords.bindings {
+            let path = format!("chords.bindings.{}", sequence);
+            match keys::parse_sequence(sequence) {
+                Ok(chord) if chord.len() < 2 => check.warnings.push(format!(
+                    "`{}` is a single key, bind it in `keys` or `hotkeys` instead",
+                    path
+                )),
+                Ok(chord) => {
+                    let leader = bindings
+                        .iter()
+                        .find(|(_, binding)| binding.key().ok() == Some(chord[0]));
+                    if let Some((leader, _)) = leader {
+                        check.warnings.push(format!(
+                            "`{}` (bound by `{}`) only starts the chord `{}` in the window",
+                            chord[0], leader, sequence
+                        ));
+                    }
+                }
+                Err(e) => check.warnings.push(format!("`{}`: {}", path, e)),
+            }
+            if defaults::hotkey(name).is_none() && defaults::key(name).is_none() {
+                check.warnings.push(format!(
+                    "`{}` is bound to `{}`, which is not in `hotkeys` or `keys`",
+                    path, name
+                ));
+            }
+        }
+        let mode_name = |mode: Mode| {
+            let value = serde_yaml::to_value(mode).unwrap_or_default();
+            value.as_str().unwrap_or_default().to_owned()
+        };
+        for (name, activation) in &config.activation {
+            if defaults::hotkey(name).is_none() && defaults::key(name).is_none() {
+                check.warnings.push(format!(
+                    "`activation.{}` is not a setting in `hotkeys` or `keys`",
+                    name
+                ));
+            } else if !activation.mode.works_globally() && config.is_global(name) {
+                check.warnings.push(format!(
+                    "`activation.{}` is {}, so `{}` only works in the window, not globally",
+                    name,
+                    mode_name(activation.mode),
+                    name
+                ));
+            }
+        }
+        for (name, activation) in defaults::ACTIVATIONS {
+            let global = !config.activation.contains_key(name) && config.is_global(name);
+            if global && !activation.mode.works_globally() {
+                check.warnings.push(format!(
+                    "`{}` is global, so it takes a double tap instead of {}, set `activation.{}.mode` to keep {} in the window",
+                    name,
+                    mode_name(activation.mode),
+                    name,
+                    mode_name(activation.mode)
+                ));
+            }
+        }
+        for (input, name) in &config.mouse {
+            let path = format!("mouse.{}", input);
+            let mouse = match input.parse::<Mouse>() {
+                Ok(mouse) => mouse,
+                Err(e) => {
+                    check.warnings.push(format!("`{}`: {}", path, e));
+                    continue;
+                }
+            };
+            let name = match name {
+                Some(name) => name,
+                None => continue,
+            };
+            if defaults::hotkey(name).is_none() && defaults::key(name).is_none() {
+                check.warnings.push(format!(
+                    "`{}` is bound to `{}`, which is not in `hotkeys` or `keys`",
+                    path, name
+                ));
+            } else if matches!(mouse.input, mouse::Input::Wheel(_))
+                && config.activation(name).mode == Mode::Hold
+            {
+                check
+                    .warnings
+                    .push(format!("`{}` can't be held, `{}` has to be", path, name));
+            }
+        }
+        for (name, binding) in config.hotkey_bindings() {
+            if let Err(e) = binding.key() {
+                check
+                    .warnings
+                    .push(format!("`{}` can't be used as a window key: {}", name, e));
+            }
+        }
+
+        check.config = Some(Config {
+            saved: serde_yaml::to_value(&config).ok(),
+            file: Some(value),
+            active_profile: active,
+            selected_profile: profile.clone(),
+            splits_settings: config.general.splits.as_deref().map(SplitsSettings::load),
+            ..config
+        });
+        Ok(check)
+    }
+    /// Every setting in effect and where its value comes from. Later layers
+    /// win: the defaults, the prefs file, the profile, the overrides and then
+    /// the splits settings. Values changed while running, like a layout
+    /// opened from the menu, are marked as such.
+    pub fn resolved(&self) -> Vec<Setting> {
+        let mut file = self.file.clone().unwrap_or(Value::Null);
+        let profiles = file.as_mapping_mut().and_then(|mapping| {
+            mapping.remove("profile");
+            mapping.remove("profiles")
+        });
+
+        let schema = schema();
+        let mut layered = default_value();
+        let mut origins = BTreeMap::new();
+        record(&layered, &Origin::Default, &mut origins);
+        overlay(&mut layered, &file);
+        record(&file, &Origin::File, &mut origins);
+        if let Some(name) = &self.active_profile {
+            if let Some(layer) = profiles.as_ref().and_then(|profiles| profiles.get(name)) {
+                overlay(&mut layered, layer);
+                record(layer, &Origin::Profile(name.clone()), &mut origins);
+            }
+        }
+        for o in OVERRIDES.read().unwrap().iter() {
+            let mut layer = Value::Null;
+            o.apply(&mut layer, &schema);
+            o.apply(&mut layered, &schema);
+            record(&layer, &Origin::Override(o.source), &mut origins);
+        }
+        if let Some(settings) = &self.splits_settings {
+            let mut layer = Mapping::new();
+            layer.insert("general".into(), serde_yaml::to_value(settings).unwrap());
+            record(&Value::Mapping(layer), &Origin::SplitsFile, &mut origins);
+        }
+
+        // The settings as they were loaded, to tell what changed since.
+        let loaded: BTreeMap<String, Value> = serde_yaml::from_value::<Config>(layered)
+            .ok()
+            .map(|mut loaded| {
+                if loaded.general.splits == self.general.splits {
+                    loaded.splits_settings = self.splits_settings.clone();
+                }
+                loaded.active_profile = self.active_profile.clone();
+                leaves(&loaded.live_value()).into_iter().collect()
+            })
+            .unwrap_or_else(BTreeMap::new);
+
+        let mut settings = Vec::new();
+        for (path, value) in leaves(&self.live_value()) {
+            let source = if path == "general.state-file" && self.general.splits.is_some() {
+                Origin::Splits
+            } else if loaded.get(&path) != Some(&value) {
+                Origin::Runtime
+            } else {
+                // A setting may have been set as a whole, like `log`.
+                ancestors(&path)
+                    .find_map(|path| origins.get(path))
+                    .cloned()
+                    .unwrap_or(Origin::Default)
+            };
+            settings.push(Setting {
+                path,
+                value,
+                source,
+            });
+        }
+        settings
+    }
+    /// The settings the app uses, with the defaults filled in and the
+    /// settings of the splits file applied.
+    fn live_value(&self) -> Value {
+        let mut value = default_value();
+        let mut ours = serde_yaml::to_value(self).unwrap();
+        if let Some(mapping) = ours.as_mapping_mut() {
+            mapping.remove("profile");
+            mapping.remove("profiles");
+        }
+        overlay(&mut value, &ours);
+
+        fn to_value(value: impl Serialize) -> Value {
+            serde_yaml::to_value(value).unwrap()
+        }
+        let mut general = Mapping::new();
+        let effective = [
+            ("layout", self.layout_path().map(to_value)),
+            ("comparison", self.comparison().map(to_value)),
+            ("timing-method", self.timing_method().map(to_value)),
+            ("auto-splitter", self.auto_splitter().map(to_value)),
+        ];
+        for (key, value) in effective {
+            if let Some(value) = value {
+                general.insert(key.into(), value);
+            }
+        }
+        if self.general.splits.is_some() {
+            general.insert("state-file".into(), to_value(self.state_file()));
+        }
+        let mut layer = Mapping::new();
+        layer.insert("general".into(), Value::Mapping(general));
+        overlay(&mut value, &Value::Mapping(layer));
+        value
+    }
+    /// Every hotkey in effect, including the defaults, with the path of its
+    /// setting.
+    fn effective_bindings(&self) -> Vec<(String, Binding)> {
+        let hotkeys = defaults::HOTKEYS
+            .iter()
+            .filter_map(|(name, _)| Some((format!("hotkeys.{}", name), self.hotkey(name)?)));
+        let keys = defaults::keys()
+            .into_iter()
+            .filter_map(|(name, _)| Some((format!("keys.{}", name), self.key(&name)?)));
+        hotkeys.chain(keys).collect()
+    }
+    /// Every hotkey set in the config, with the path of its setting.
+    fn hotkey_bindings(&self) -> Vec<(String, Binding)> {
+        let hotkeys = self
+            .hotkeys
+            .iter()
+            .map(|(name, binding)| (format!("hotkeys.{}", name), *binding));
+        let keys = self
+            .keys
+            .iter()
+            .map(|(name, binding)| (format!("keys.{}", name), *binding));
+        hotkeys.chain(keys).collect()
     }
     pub fn window_size(&self) -> [usize; 2] {
         self.window.size()
@@ -137,12 +938,14 @@ impl Config {
         run.fix_splits();
         Some(run)
     }
-    pub fn set_use_global_hotkeys(&mut self, on: bool) {
-        self.general.use_global_hotkeys = Some(on);
-    }
     pub fn use_global_hotkeys(&self) -> bool {
         self.general.use_global_hotkeys.unwrap_or(false)
     }
+    /// Whether the setting `name` in `keys` is registered globally when
+    /// global hotkeys are on.
+    pub fn is_global_key(&self, name: &str) -> bool {
+        self.general.global_keys.iter().any(|key| key == name)
+    }
     pub fn parse_run_or_default(&self) -> Run {
         self.parse_run().unwrap_or_else(|| {
             let mut run = Run::new();
@@ -154,15 +957,47 @@ impl Config {
     }
 
     pub fn is_game_time(&self) -> bool {
-        self.general.timing_method == Some(TimingMethod::GameTime)
+        self.timing_method() == Some(TimingMethod::GameTime)
     }
 
+    fn layout_path(&self) -> Option<PathBuf> {
+        self.splits_settings
+            .as_ref()
+            .and_then(SplitsSettings::layout)
+            .or_else(|| self.general.layout.clone())
+    }
+    fn comparison(&self) -> Option<&str> {
+        self.splits_settings
+            .as_ref()
+            .and_then(|s| s.comparison.as_deref())
+            .or(self.general.comparison.as_deref())
+    }
+    fn timing_method(&self) -> Option<TimingMethod> {
+        self.splits_settings
+            .as_ref()
+            .and_then(|s| s.timing_method)
+            .or(self.general.timing_method)
+    }
+    fn auto_splitter(&self) -> Option<PathBuf> {
+        self.splits_settings
+            .as_ref()
+            .and_then(SplitsSettings::auto_splitter)
+            .or_else(|| self.general.auto_splitter.clone())
+    }
+
+    /// Sets the layout of the loaded splits file, or the global one if there
+    /// is none.
     pub fn set_layout_path(&mut self, path: &Path) {
-        self.general.layout = Some(path.to_path_buf());
+        if let Some(settings) = &mut self.splits_settings {
+            settings.set_layout(path);
+            settings.save();
+        } else {
+            self.general.layout = Some(path.to_path_buf());
+        }
     }
     pub fn parse_layout(&self) -> Option<Layout> {
-        let path = self.general.layout.as_ref()?;
-        let file = fs::read_to_string(path).ok()?;
+        let path = self.layout_path()?;
+        let file = fs::read_to_string(&path).ok()?;
         if let Ok(settings) = LayoutSettings::from_json(Cursor::new(&file)) {
             return Some(Layout::from_settings(settings));
         }
@@ -177,38 +1012,80 @@ impl Config {
     }
     pub fn set_splits_path(&mut self, path: &Path) {
         self.general.splits = Some(path.to_path_buf());
+        self.splits_settings = Some(SplitsSettings::load(path));
     }
 
+    /// Registers the timer hotkeys globally, with the defaults for the ones
+    /// that aren't set.
     pub fn create_hotkey_system(&self, timer: SharedTimer) -> Option<HotkeySystem> {
-        HotkeySystem::with_config(timer, self.hotkeys).ok()
+        let mut hotkeys = HotkeyConfig::default();
+        hotkeys.split = self.global_hotkey("split");
+        hotkeys.reset = self.global_hotkey("reset");
+        hotkeys.undo = self.global_hotkey("undo");
+        hotkeys.skip = self.global_hotkey("skip");
+        hotkeys.pause = self.global_hotkey("pause");
+        hotkeys.undo_all_pauses = self.global_hotkey("undo_all_pauses");
+        hotkeys.previous_comparison = self.global_hotkey("previous_comparison");
+        hotkeys.next_comparison = self.global_hotkey("next_comparison");
+        hotkeys.toggle_timing_method = self.global_hotkey("toggle_timing_method");
+        HotkeySystem::with_config(timer, hotkeys).ok()
+    }
+    /// The timer hotkey with the name `name` if the hotkey system handles it.
+    /// Scancodes, hotkeys that aren't triggered by a single press and hotkeys
+    /// with a cooldown are left to the app.
+    pub fn global_hotkey(&self, name: &str) -> Option<Hotkey> {
+        if self.activation(name).is_plain() {
+            self.hotkey(name)?.hotkey()
+        } else {
+            None
+        }
     }
 
     pub fn configure_timer(&self, timer: &mut Timer) {
-        if self.is_game_time() {
-            timer.set_current_timing_method(TimingMethod::GameTime);
-        }
-        if let Some(comparison) = &self.general.comparison {
-            timer.set_current_comparison(&**comparison).ok();
+        timer.set_current_timing_method(if self.is_game_time() {
+            TimingMethod::GameTime
+        } else {
+            TimingMethod::RealTime
+        });
+        if let Some(comparison) = self.comparison() {
+            timer.set_current_comparison(comparison).ok();
         }
     }
 
     pub fn save_splits(&self, timer: &Timer) {
         if let Some(path) = &self.general.splits {
             let mut buf = String::new();
-            let _ = save_timer(timer, &mut buf);
-            // FIXME: Don't ignore not being able to save.
-            let _ = fs::write(path, &buf);
+            if let Err(e) = save_timer(timer, &mut buf) {
+                log::error!("Couldn't save the splits: {}", e);
+                return;
+            }
+            persist::save(path, buf, Some(self.paths.backups()));
         }
     }
+    /// Saves the timer state, rotating the previous one into the backups if
+    /// `rotate` is set.
+    pub fn save_state(&self, state: &str, rotate: bool) {
+        let backups = rotate.then(|| self.paths.backups());
+        persist::save(&self.state_file(), state, backups);
+    }
 
+    /// Sets up logging to the log file, if there is one. A relative path is
+    /// relative to the log directory.
     pub fn setup_logging(&self) {
         if let Some(log) = &self.log {
+            let path = self.paths.log_dir().join(&log.path);
+            if log.path.is_relative() {
+                paths::adopt(&log.path, &path);
+            }
+            if let Some(dir) = path.parent() {
+                let _ = fs::create_dir_all(dir);
+            }
             if let Ok(log_file) = fs::OpenOptions::new()
                 .create(true)
                 .write(true)
                 .append(!log.clear)
                 .truncate(log.clear)
-                .open(&log.path)
+                .open(&path)
             {
                 fern::Dispatch::new()
                     .format(|out, message, record| {
@@ -254,69 +1131,385 @@ impl Config {
         Ok(window)
     }
     */
-    pub fn build_marker_client(&self) -> stream_markers::Client {
-        stream_markers::Client::new(self.connections.twitch.as_deref())
+    /// The client for the stream markers, if any are set up and it could be
+    /// started.
+    pub fn build_marker_client(&self) -> Option<stream_markers::Client> {
+        let connections = &self.connections;
+        if connections.twitch.is_none()
+            && connections.marker_file.is_none()
+            && connections.webhooks.is_empty()
+        {
+            return None;
+        }
+        let mut client = stream_markers::Client::new()?;
+        let handle = client.handle();
+        if let Some(token) = &self.connections.twitch {
+            client.add(stream_markers::Twitch::new(&handle, token));
+        }
+        if let Some(path) = &self.connections.marker_file {
+            client.add(stream_markers::File::new(&handle, path));
+        }
+        for url in &self.connections.webhooks {
+            client.add(stream_markers::Webhook::new(&handle, url));
+        }
+        Some(client)
     }
 
     pub fn maybe_load_auto_splitter(&self, runtime: &auto_splitting::Runtime) {
-        if let Some(auto_splitter) = &self.general.auto_splitter {
-            if let Err(e) = runtime.load_script_blocking(auto_splitter.clone()) {
+        if let Some(auto_splitter) = self.auto_splitter() {
+            if let Err(e) = runtime.load_script_blocking(auto_splitter) {
                 log::error!("Auto Splitter failed to load: {}", ErrorChain(&e));
             }
         }
     }
 
-    pub fn reset_key(&self) -> Hotkey {
+    /// The timer hotkey with the name `name` in the `hotkeys` section, or its
+    /// default.
+    pub fn hotkey(&self, name: &str) -> Option<Binding> {
         self.hotkeys
-            .reset
-            .unwrap_or_else(|| "Numpad3".parse().unwrap())
+            .get(name)
+            .copied()
+            .or_else(|| defaults::hotkey(name)?.parse().ok())
     }
-    pub fn split_key(&self) -> Hotkey {
-        self.hotkeys
-            .split
-            .unwrap_or_else(|| "Numpad1".parse().unwrap())
+    /// The key setting with the name `name`, in `hotkeys` or `keys`.
+    pub fn binding(&self, name: &str) -> Option<Binding> {
+        self.hotkey(name).or_else(|| self.key(name))
     }
-    pub fn undo_key(&self) -> Hotkey {
-        self.hotkeys
-            .undo
-            .unwrap_or_else(|| "Numpad8".parse().unwrap())
+    /// Binds the key setting with the name `name`, in `hotkeys` if it is one
+    /// of the timer hotkeys and in `keys` otherwise.
+    pub fn set_binding(&mut self, name: &str, binding: Binding) {
+        if defaults::hotkey(name).is_some() {
+            self.hotkeys.insert(name.to_owned(), binding);
+        } else {
+            self.keys.insert(name.to_owned(), binding);
+        }
     }
-    pub fn skip_key(&self) -> Hotkey {
-        self.hotkeys
-            .skip
-            .unwrap_or_else(|| "Numpad2".parse().unwrap())
+    /// How the key with the name `name` in `hotkeys` or `keys` triggers its
+    /// action. Unless set in `activation`, a key that is registered globally
+    /// takes a double tap instead of being held or confirmed, so turning
+    /// global hotkeys on neither moves it into the window nor lets a single
+    /// press trigger it.
+    pub fn activation(&self, name: &str) -> Activation {
+        match self.activation.get(name) {
+            Some(activation) => *activation,
+            None if self.is_global(name) => defaults::activation(name).global(),
+            None => defaults::activation(name),
+        }
     }
-    pub fn pause_key(&self) -> Hotkey {
-        self.hotkeys
-            .pause
-            .unwrap_or_else(|| "Numpad5".parse().unwrap())
+    /// Whether the key with the name `name` is registered globally, as long
+    /// as it is triggered in a way that works globally.
+    pub fn is_global(&self, name: &str) -> bool {
+        let binding = if defaults::hotkey(name).is_some() {
+            self.hotkey(name)
+        } else if self.is_global_key(name) {
+            self.key(name)
+        } else {
+            None
+        };
+        self.use_global_hotkeys() && binding.map_or(false, |binding| binding.hotkey().is_some())
     }
-    pub fn undo_all_key(&self) -> Hotkey {
-        self.hotkeys
-            .undo_all_pauses
-            .unwrap_or_else(|| "Numpad7".parse().unwrap())
+    /// The key sequences, by the name of the setting they do the same as.
+    pub fn chords(&self) -> &BTreeMap<String, String> {
+        &self.chords.bindings
     }
-    pub fn prev_key(&self) -> Hotkey {
-        self.hotkeys
-            .previous_comparison
-            .unwrap_or_else(|| "Numpad4".parse().unwrap())
+    /// The command to run when the timer does `action`.
+    pub fn hook(&self, action: &str) -> Option<&str> {
+        self.hooks.get(action).map(String::as_str)
     }
-    pub fn next_key(&self) -> Hotkey {
-        self.hotkeys
-            .next_comparison
-            .unwrap_or_else(|| "Numpad6".parse().unwrap())
+    pub fn chord_timeout(&self) -> Duration {
+        Duration::from_millis(self.chords.timeout)
     }
-    pub fn toggle_timing_method_key(&self) -> Hotkey {
-        self.hotkeys
-            .toggle_timing_method
-            .unwrap_or_else(|| "Numpad9".parse().unwrap())
+    /// The mouse buttons and wheel directions, with the defaults, by the
+    /// name of the setting they do the same as.
+    pub fn mouse(&self) -> BTreeMap<String, String> {
+        let mut bindings: BTreeMap<String, String> = defaults::MOUSE
+            .iter()
+            .map(|(input, name)| (input.to_string(), name.to_string()))
+            .collect();
+        for (input, name) in &self.mouse {
+            match name {
+                Some(name) => bindings.insert(input.clone(), name.clone()),
+                None => bindings.remove(input),
+            };
+        }
+        bindings
     }
-    pub fn keys(&self) -> &HashMap<String, Hotkey> {
-        &self.keys
+    /// The app key with the name `name` in the `keys` section, or its
+    /// default.
+    pub fn key(&self, name: &str) -> Option<Binding> {
+        self.keys
+            .get(name)
+            .copied()
+            .or_else(|| defaults::key(name)?.parse().ok())
     }
+    pub fn recent_splits(&self) -> &[PathBuf] {
+        &self.recent.splits
+    }
+    pub fn recent_layouts(&self) -> &[PathBuf] {
+        &self.recent.layouts
+    }
+    pub fn splits_dir(&self) -> Option<&Path> {
+        self.recent.splits_dir.as_deref()
+    }
+    pub fn layout_dir(&self) -> Option<&Path> {
+        self.recent.layout_dir.as_deref()
+    }
+    /// Moves the splits file to the front of the recent ones and remembers
+    /// its directory for the file dialog.
+    pub fn add_recent_splits(&mut self, path: &Path) {
+        push_recent(&mut self.recent.splits, path);
+        self.recent.splits_dir = path.parent().map(Path::to_path_buf);
+    }
+    pub fn add_recent_layout(&mut self, path: &Path) {
+        push_recent(&mut self.recent.layouts, path);
+        self.recent.layout_dir = path.parent().map(Path::to_path_buf);
+    }
+    /// Forgets a file that couldn't be opened anymore.
+    pub fn remove_recent(&mut self, path: &Path) {
+        self.recent.splits.retain(|p| p != path);
+        self.recent.layouts.retain(|p| p != path);
+    }
+    /// Sets the comparison of the loaded splits file, or the global one if
+    /// there is none.
     pub fn set_comparison(&mut self, comparison: &str) {
-        self.general.comparison = Some(comparison.to_string());
-        self.save_config();
+        if let Some(settings) = &mut self.splits_settings {
+            settings.comparison = Some(comparison.to_string());
+            settings.save();
+        } else {
+            self.general.comparison = Some(comparison.to_string());
+            self.save_config();
+        }
+    }
+}
+
+/// The name of the state file of a splits file, unique to its path so splits
+/// files of the same name in different directories don't share one.
+fn splits_state_file(splits: &Path) -> PathBuf {
+    paths::unique_name(splits).with_extension("lsz")
+}
+
+/// The line ranges of the top level keys of a YAML document in block style.
+fn sections(lines: &[&str]) -> Vec<Range<usize>> {
+    let starts: Vec<usize> = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| {
+            !line.starts_with(|c: char| c.is_whitespace() || c == '#') && line.contains(':')
+        })
+        .map(|(i, _)| i)
+        .collect();
+    starts
+        .iter()
+        .enumerate()
+        .map(|(n, &start)| start..starts.get(n + 1).copied().unwrap_or(lines.len()))
+        .collect()
+}
+
+/// The top level section `text` of a prefs file of version `from`, upgraded,
+/// or none if upgrading doesn't change it.
+fn upgraded_section(text: &str, from: u64) -> Option<Value> {
+    let mut original = serde_yaml::from_str::<Value>(text).ok()?;
+    original.as_mapping_mut()?.remove("version");
+    let mut upgraded = original.clone();
+    upgraded
+        .as_mapping_mut()?
+        .insert("version".into(), from.into());
+    migrate::migrate(&mut upgraded).ok()?;
+    upgraded.as_mapping_mut()?.remove("version");
+    (upgraded != original).then(|| upgraded)
+}
+
+/// Collects the keys in `value` that don't exist in `schema`.
+fn unknown_keys(schema: &Value, value: &Value, path: &str, warnings: &mut Vec<String>) {
+    if let (Value::Mapping(schema), Value::Mapping(value)) = (schema, value) {
+        for (key, value) in value {
+            let name = match key {
+                Value::String(name) => name.as_str(),
+                _ => continue,
+            };
+            let path = if path.is_empty() {
+                name.to_string()
+            } else {
+                format!("{}.{}", path, name)
+            };
+            match schema.get(key) {
+                Some(_) if path == "keys" || path == "profiles" || path == "hooks" => {}
+                Some(schema) => unknown_keys(schema, value, &path, warnings),
+                None => warnings.push(format!("unknown setting `{}`", path)),
+            }
+        }
+    }
+}
+
+/// Every setting there is, for checking the names of settings against.
+fn schema() -> Value {
+    serde_yaml::to_value(Config {
+        log: Some(Log::default()),
+        ..Default::default()
+    })
+    .unwrap()
+}
+
+/// Every setting with its default value.
+fn default_value() -> Value {
+    let mut value = serde_yaml::to_value(Config {
+        version: migrate::VERSION,
+        paths: Paths::defaults(),
+        ..Default::default()
+    })
+    .unwrap();
+    let mut general = Mapping::new();
+    general.insert("use-global-hotkeys".into(), false.into());
+    general.insert("state-file".into(), defaults::STATE_FILE.into());
+    let hotkeys: Mapping = defaults::HOTKEYS
+        .iter()
+        .map(|(name, key)| ((*name).into(), (*key).into()))
+        .collect();
+    let keys: Mapping = defaults::keys()
+        .into_iter()
+        .map(|(name, key)| (name.into(), key.into()))
+        .collect();
+    let activation: Mapping = defaults::ACTIVATIONS
+        .iter()
+        .map(|(name, activation)| ((*name).into(), serde_yaml::to_value(activation).unwrap()))
+        .collect();
+    let mut explicit = Mapping::new();
+    explicit.insert("general".into(), Value::Mapping(general));
+    explicit.insert("activation".into(), Value::Mapping(activation));
+    let mouse: Mapping = defaults::MOUSE
+        .iter()
+        .map(|(input, name)| ((*input).into(), (*name).into()))
+        .collect();
+    explicit.insert("mouse".into(), Value::Mapping(mouse));
+    explicit.insert("hotkeys".into(), Value::Mapping(hotkeys));
+    explicit.insert("keys".into(), Value::Mapping(keys));
+    overlay(&mut value, &Value::Mapping(explicit));
+    value
+}
+
+/// Every value that isn't a mapping, or is an empty one, by its path.
+fn leaves(value: &Value) -> Vec<(String, Value)> {
+    fn collect(value: &Value, path: &str, out: &mut Vec<(String, Value)>) {
+        match value {
+            Value::Mapping(mapping) if !mapping.is_empty() => {
+                for (key, value) in mapping {
+                    if let Some(key) = key.as_str() {
+                        let path = if path.is_empty() {
+                            key.to_owned()
+                        } else {
+                            format!("{}.{}", path, key)
+                        };
+                        collect(value, &path, out);
+                    }
+                }
+            }
+            _ if path.is_empty() => {}
+            _ => out.push((path.to_owned(), value.clone())),
+        }
+    }
+    let mut out = Vec::new();
+    collect(value, "", &mut out);
+    out
+}
+
+fn record(layer: &Value, origin: &Origin, origins: &mut BTreeMap<String, Origin>) {
+    for (path, _) in leaves(layer) {
+        origins.insert(path, origin.clone());
+    }
+}
+
+/// `a.b.c`, `a.b` and `a`.
+fn ancestors(path: &str) -> impl Iterator<Item = &str> {
+    std::iter::successors(Some(path), |path| Some(path.rsplit_once('.')?.0))
+}
+
+/// Parses the config with the overrides of the profile and then the other
+/// overrides applied on top of the base settings. Each layer is checked on
+/// its own so errors can be blamed on the right one.
+fn effective(
+    value: &Value,
+    profile: Option<&str>,
+    overrides: &[Override],
+    schema: &Value,
+) -> Result<Config, String> {
+    let mut value = value.clone();
+    if let Some(name) = profile {
+        let profile = value
+            .get("profiles")
+            .and_then(|profiles| profiles.get(name))
+            .cloned()
+            .ok_or_else(|| format!("there is no profile named `{}`", name))?;
+        overlay(&mut value, &profile);
+        serde_yaml::from_value::<Config>(value.clone())
+            .map_err(|e| format!("profiles.{}: {}", name, e))?;
+    }
+    for o in overrides {
+        o.apply(&mut value, schema);
+        serde_yaml::from_value::<Config>(value.clone()).map_err(|e| format!("{}: {}", o, e))?;
+    }
+    Ok(serde_yaml::from_value(value).unwrap())
+}
+
+fn overlay(base: &mut Value, over: &Value) {
+    match (base, over) {
+        (Value::Mapping(base), Value::Mapping(over)) => {
+            for (key, value) in over {
+                match base.get_mut(key) {
+                    Some(base) => overlay(base, value),
+                    None => {
+                        base.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+        }
+        (base, over) => *base = over.clone(),
+    }
+}
+
+/// The overrides of the profile `name` in the prefs file, created if missing.
+fn profile_mut<'a>(file: &'a mut Value, name: &str) -> &'a mut Value {
+    let mut value = file;
+    for key in ["profiles", name] {
+        if !value.is_mapping() {
+            *value = Value::Mapping(Mapping::new());
+        }
+        let mapping = value.as_mapping_mut().unwrap();
+        let key = Value::String(key.to_owned());
+        if !mapping.contains_key(&key) {
+            mapping.insert(key.clone(), Value::Mapping(Mapping::new()));
+        }
+        value = mapping.get_mut(&key).unwrap();
+    }
+    value
+}
+
+/// Applies the changes between `base` and `ours` to `theirs`, recursing into
+/// mappings so that edits to unrelated keys on either side are kept.
+fn merge(base: &Value, ours: &Value, theirs: &mut Value) {
+    if let (Value::Mapping(base), Value::Mapping(ours), Value::Mapping(theirs)) =
+        (base, ours, &mut *theirs)
+    {
+        for (key, value) in ours {
+            match (base.get(key), theirs.get_mut(key)) {
+                (Some(base), _) if base == value => {}
+                (Some(base), Some(theirs)) => merge(base, value, theirs),
+                (Some(base), None) if base.is_mapping() && value.is_mapping() => {
+                    let mut changes = Value::Mapping(Mapping::new());
+                    merge(base, value, &mut changes);
+                    theirs.insert(key.clone(), changes);
+                }
+                _ => {
+                    theirs.insert(key.clone(), value.clone());
+                }
+            }
+        }
+        for key in base.keys() {
+            if !ours.contains_key(key) {
+                theirs.remove(key);
+            }
+        }
+    } else if base != ours {
+        *theirs = ours.clone();
     }
 }
 
@@ -337,3 +1530,184 @@ impl fmt::Display for ErrorChain<'_> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{MutexGuard, PoisonError};
+
+    lazy_static! {
+        static ref PREFS: Mutex<()> = Mutex::new(());
+    }
+
+    /// Points the app at a prefs file of its own in the directory `name`,
+    /// with the text `text` returns for the directory. The prefs file is
+    /// global, so it is only used while the guard is held.
+    fn use_prefs(
+        name: &str,
+        text: impl FnOnce(&Path) -> String,
+    ) -> (MutexGuard<'static, ()>, PathBuf) {
+        let guard = PREFS.lock().unwrap_or_else(PoisonError::into_inner);
+        let dir = std::env::temp_dir().join(format!("livesplit-one-{}-test", name));
+        fs::create_dir_all(&dir).unwrap();
+        let prefs = dir.join("prefs.yaml");
+        fs::write(&prefs, text(&dir)).unwrap();
+        Config::set_config_file(prefs);
+        (guard, dir)
+    }
+
+    #[test]
+    fn switching_profiles_keeps_the_splits_state_file() {
+        let (_prefs, dir) = use_prefs("profiles", |dir| {
+            format!(
+                "version: {}\n\
+                 general:\n  splits: {:?}\n\
+                 paths:\n  state: {:?}\n\
+                 profiles:\n  wide:\n    window:\n      width: 600\n",
+                migrate::VERSION,
+                dir.join("run.lss"),
+                dir
+            )
+        });
+
+        let base = Config::parse(&Profile::Default).unwrap();
+        let wide = Config::parse(&base.next_profile()).unwrap();
+        assert_eq!(wide.active_profile(), Some("wide"));
+        assert_eq!(wide.state_file(), base.state_file());
+        assert_ne!(wide.state_file(), dir.join(defaults::STATE_FILE));
+    }
+
+    #[test]
+    fn cycling_leaves_the_default_profile() {
+        let (_prefs, _) = use_prefs("default-profile", |_| {
+            format!(
+                "version: {}\n\
+                 profile: b\n\
+                 profiles:\n  a:\n    window:\n      width: 1\n  b:\n    window:\n      width: 2\n",
+                migrate::VERSION
+            )
+        });
+
+        let mut config = Config::parse(&Profile::Default).unwrap();
+        let mut seen = Vec::new();
+        for _ in 0..3 {
+            seen.push(config.active_profile().map(str::to_owned));
+            config = Config::parse(&config.next_profile()).unwrap();
+        }
+        assert_eq!(seen, [Some("b".to_owned()), None, Some("a".to_owned())]);
+        assert_eq!(config.active_profile(), Some("b"));
+
+        // Reading the file again keeps the base settings picked.
+        let base = Config::parse(&Profile::Base).unwrap();
+        let reloaded = Config::parse(&base.selected_profile()).unwrap();
+        assert_eq!(reloaded.active_profile(), None);
+    }
+
+    #[test]
+    fn overrides_win_over_the_file_and_the_command_line_over_the_environment() {
+        let (_prefs, _) = use_prefs("overrides", |_| {
+            format!(
+                "version: {}\ngeneral:\n  comparison: file\n  timing-method: GameTime\n",
+                migrate::VERSION
+            )
+        });
+        let env = Override::new(
+            overrides::Source::Environment,
+            "general.comparison",
+            "env".into(),
+        );
+        let arg = Override::parse_arg("general.comparison=arg").unwrap();
+
+        Config::set_overrides(vec![env.clone()]);
+        let config = Config::parse(&Profile::Default).unwrap();
+        assert_eq!(config.comparison(), Some("env"));
+        assert!(config.is_game_time());
+
+        Config::set_overrides(vec![env, arg]);
+        let config = Config::parse(&Profile::Default).unwrap();
+        assert_eq!(config.comparison(), Some("arg"));
+        Config::set_overrides(Vec::new());
+    }
+
+    #[test]
+    fn overrides_of_settings_changed_while_running_are_dropped() {
+        let (_prefs, dir) = use_prefs("stale-overrides", |_| {
+            format!("version: {}\n", migrate::VERSION)
+        });
+        let splits = Value::String(dir.join("a.lss").display().to_string());
+        Config::set_overrides(vec![
+            Override::new(overrides::Source::CommandLine, "general.splits", splits),
+            Override::parse_arg("window.fps=40").unwrap(),
+        ]);
+        let mut config = Config::parse(&Profile::Default).unwrap();
+        config.drop_stale_overrides();
+        assert_eq!(OVERRIDES.read().unwrap().len(), 2);
+
+        config.set_splits_path(&dir.join("b.lss"));
+        config.drop_stale_overrides();
+        assert_eq!(OVERRIDES.read().unwrap().len(), 1);
+        // Only what is in the file is left, the app saves the new one there.
+        let config = Config::parse(&Profile::Default).unwrap();
+        assert_eq!(config.splits_path(), None);
+        assert_eq!(config.window.fps, 40.0);
+        Config::set_overrides(Vec::new());
+    }
+
+    fn yaml(text: &str) -> Value {
+        serde_yaml::from_str(text).unwrap()
+    }
+
+    #[test]
+    fn errors_in_old_prefs_point_at_the_file_as_written() {
+        let (_prefs, dir) = use_prefs("check", |_| {
+            "hotkeys:\n  split: Left\n  next_comparision: Right\n\
+             \n\
+             window:\n  width: wide\n"
+                .to_owned()
+        });
+
+        let check = Config::check(dir.join("prefs.yaml"), &Profile::Default).unwrap();
+        assert_eq!(check.errors.len(), 1);
+        assert!(check.errors[0].contains("line 6"), "{}", check.errors[0]);
+        assert_eq!(check.warnings.len(), 1);
+    }
+
+    #[test]
+    fn merging_keeps_what_was_added_to_the_file() {
+        let base = yaml("general:\n  comparison: Best Segments\nwindow:\n  width: 180\n");
+        let ours = yaml("general:\n  comparison: Average Segments\nwindow:\n  width: 180\n");
+        let mut theirs = yaml(
+            "general:\n  comparison: Best Segments\n  layout: mine.ls1l\n\
+             window:\n  width: 200\n\
+             custom:\n  anything: 1\n",
+        );
+        merge(&base, &ours, &mut theirs);
+        let expected = yaml(
+            "general:\n  comparison: Average Segments\n  layout: mine.ls1l\n\
+             window:\n  width: 200\n\
+             custom:\n  anything: 1\n",
+        );
+        assert_eq!(theirs, expected);
+    }
+
+    #[test]
+    fn merging_adds_new_sections_and_removes_deleted_keys() {
+        let base = yaml("keys:\n  open: Ctrl+O\n  save: Ctrl+S\n");
+        let ours = yaml("keys:\n  open: Ctrl+O\nhotkeys:\n  split: Space\n");
+        let mut theirs = yaml("keys:\n  open: Ctrl+O\n  save: Ctrl+S\n  quit: Ctrl+Q\n");
+        merge(&base, &ours, &mut theirs);
+        let expected = yaml("keys:\n  open: Ctrl+O\n  quit: Ctrl+Q\nhotkeys:\n  split: Space\n");
+        assert_eq!(theirs, expected);
+    }
+
+    #[test]
+    fn a_locked_config_is_not_saved() {
+        let mut config = Config {
+            locked: true,
+            ..Default::default()
+        };
+        let written = WRITTEN.lock().unwrap().len();
+        assert!(!config.save_config());
+        assert_eq!(WRITTEN.lock().unwrap().len(), written);
+    }
+}
@@ -0,0 +1,101 @@
+use crate::activation::{Activation, Mode};
+
+/// The state file used without a splits file, in the state directory.
+pub const STATE_FILE: &str = "livesplit_state.lsz";
+
+pub const WINDOW_WIDTH: usize = 300;
+pub const WINDOW_HEIGHT: usize = 500;
+pub const ALWAYS_ON_TOP: bool = false;
+pub const TRANSPARENCY: bool = true;
+pub const FPS: f32 = 60.0;
+
+/// How many old versions of each file are kept in the backups.
+pub const KEEP_BACKUPS: usize = 5;
+
+/// How many splits files and layouts are remembered. They can be opened with
+/// the `recent_splits_<n>` and `recent_layout_<n>` keys.
+pub const MAX_RECENT: usize = 9;
+
+/// How long to wait for the next key of a chord.
+pub const CHORD_TIMEOUT_MS: u64 = 1500;
+
+pub const HOLD_MS: u64 = 500;
+pub const DOUBLE_TAP_MS: u64 = 400;
+pub const CONFIRM_MS: u64 = 3000;
+
+/// The actions that a single stray key press must not trigger, by the name of
+/// their key. All others happen when their key is pressed. These take a double
+/// tap instead when their key is registered globally, see
+/// `Config::activation`.
+pub const ACTIVATIONS: [(&str, Activation); 2] = [
+    ("reset", Activation::new(Mode::Hold, None)),
+    ("quit", Activation::new(Mode::Confirm, None)),
+];
+
+pub fn activation(name: &str) -> Activation {
+    ACTIVATIONS
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map_or(Activation::PRESS, |(_, activation)| *activation)
+}
+
+/// The timer hotkeys, by their name in the `hotkeys` section.
+pub const HOTKEYS: [(&str, &str); 9] = [
+    ("split", "Numpad1"),
+    ("reset", "Numpad3"),
+    ("undo", "Numpad8"),
+    ("skip", "Numpad2"),
+    ("pause", "Numpad5"),
+    ("undo_all_pauses", "Numpad7"),
+    ("previous_comparison", "Numpad4"),
+    ("next_comparison", "Numpad6"),
+    ("toggle_timing_method", "Numpad9"),
+];
+
+/// The app's keys, by their name in the `keys` section. The keys for the
+/// recent files are in `keys()`.
+pub const KEYS: [(&str, &str); 13] = [
+    ("open", "Ctrl+O"),
+    ("save", "Ctrl+S"),
+    ("quit", "Ctrl+Q"),
+    ("hide", "Ctrl+T"),
+    ("layout", "Ctrl+L"),
+    ("layout_up", "ArrowUp"),
+    ("layout_down", "ArrowDown"),
+    ("profile", "Ctrl+P"),
+    ("next_splits", "Alt+Period"),
+    ("next_layout", "Ctrl+Period"),
+    ("bind", "Ctrl+B"),
+    ("lock", "ScrollLock"),
+    ("help", "F1"),
+];
+
+/// The mouse bindings, by the mouse input. The wheel scrolls the layout.
+pub const MOUSE: [(&str, &str); 2] = [("WheelUp", "layout_down"), ("WheelDown", "layout_up")];
+
+/// Every key that can be set in the `keys` section, with its default.
+pub fn keys() -> Vec<(String, String)> {
+    let mut keys: Vec<(String, String)> = KEYS
+        .iter()
+        .map(|(name, key)| (name.to_string(), key.to_string()))
+        .collect();
+    for n in 1..=MAX_RECENT {
+        keys.push((format!("recent_splits_{}", n), format!("Alt+Digit{}", n)));
+        keys.push((format!("recent_layout_{}", n), format!("Ctrl+Digit{}", n)));
+    }
+    keys
+}
+
+pub fn hotkey(name: &str) -> Option<&'static str> {
+    HOTKEYS
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, key)| *key)
+}
+
+pub fn key(name: &str) -> Option<String> {
+    keys()
+        .into_iter()
+        .find(|(n, _)| n == name)
+        .map(|(_, key)| key)
+}
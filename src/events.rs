@@ -0,0 +1,148 @@
+use lazy_static::lazy_static;
+use livesplit_core::{Timer, TimerPhase, TimerState};
+use std::{
+    mem,
+    sync::{
+        mpsc::{channel, Sender},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::SystemTime,
+};
+
+/// A single change of the timer, as reported by the timer itself.
+pub struct TimerEvent {
+    pub action: String,
+    pub phase: TimerPhase,
+    pub split_name: String,
+    /// The number of the attempt the timer is on, or was on last.
+    pub attempt: u32,
+    /// The game and category of the run.
+    pub run_name: String,
+    /// When the change happened.
+    pub time: SystemTime,
+    /// The serialized timer state after the change.
+    pub state: String,
+}
+
+lazy_static! {
+    static ref SUBSCRIBERS: Mutex<Subscribers> = Mutex::new(Subscribers::default());
+    static ref RUN: Mutex<Run> = Mutex::new(Run::default());
+}
+
+/// What the events tell about the run, which the timer can't be asked for
+/// while it is reporting a change.
+struct Run {
+    attempt: u32,
+    name: String,
+    phase: TimerPhase,
+}
+
+impl Default for Run {
+    fn default() -> Self {
+        Self {
+            attempt: 0,
+            name: String::new(),
+            phase: TimerPhase::NotRunning,
+        }
+    }
+}
+
+/// Starts following the run of the timer. Call it whenever the timer or its
+/// state is replaced, the attempts are counted from there.
+pub fn track(timer: &Timer) {
+    *RUN.lock().unwrap() = Run {
+        attempt: timer.run().attempt_count(),
+        name: timer.run().extended_name(false).into_owned(),
+        phase: timer.current_phase(),
+    };
+}
+
+#[derive(Default)]
+struct Subscribers {
+    next_id: u64,
+    subscribers: Vec<Subscriber>,
+}
+
+struct Subscriber {
+    id: u64,
+    tx: Sender<Arc<TimerEvent>>,
+    thread: JoinHandle<()>,
+}
+
+/// Keeps a subscriber alive. Dropping it unsubscribes and lets the
+/// subscriber's thread finish.
+pub struct Subscription {
+    id: u64,
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        SUBSCRIBERS
+            .lock()
+            .unwrap()
+            .subscribers
+            .retain(|subscriber| subscriber.id != self.id);
+    }
+}
+
+/// Calls `f` on its own thread for every timer event, in order. Subscribers
+/// run outside of the timer callback, so they are free to lock the timer.
+pub fn subscribe(name: &str, mut f: impl FnMut(&TimerEvent) + Send + 'static) -> Subscription {
+    let (tx, rx) = channel::<Arc<TimerEvent>>();
+    let thread = thread::Builder::new()
+        .name(format!("{} events", name))
+        .spawn(move || {
+            for event in rx {
+                f(&event);
+            }
+        })
+        .unwrap();
+
+    let mut subscribers = SUBSCRIBERS.lock().unwrap();
+    let id = subscribers.next_id;
+    subscribers.next_id += 1;
+    subscribers.subscribers.push(Subscriber { id, tx, thread });
+    Subscription { id }
+}
+
+/// The timer change callback. Hands the change to every subscriber.
+pub fn publish(timer_state: &TimerState) {
+    let (attempt, run_name) = {
+        let mut run = RUN.lock().unwrap();
+        // Starting the timer starts the next attempt.
+        if run.phase == TimerPhase::NotRunning && timer_state.phase != TimerPhase::NotRunning {
+            run.attempt += 1;
+        }
+        run.phase = timer_state.phase;
+        (run.attempt, run.name.clone())
+    };
+    let event = Arc::new(TimerEvent {
+        action: format!("{:?}", timer_state.action),
+        phase: timer_state.phase,
+        split_name: timer_state.split_name.to_string(),
+        attempt,
+        run_name,
+        time: SystemTime::now(),
+        state: timer_state.to_json(),
+    });
+    SUBSCRIBERS
+        .lock()
+        .unwrap()
+        .subscribers
+        .retain(|subscriber| subscriber.tx.send(event.clone()).is_ok());
+}
+
+/// Stops handing out timer events and waits until every subscriber has dealt
+/// with the ones it already got. Call it when exiting, before anything the
+/// subscribers write is flushed.
+pub fn drain() {
+    let subscribers = mem::take(&mut SUBSCRIBERS.lock().unwrap().subscribers);
+    let threads: Vec<JoinHandle<()>> = subscribers
+        .into_iter()
+        .map(|subscriber| subscriber.thread)
+        .collect();
+    for thread in threads {
+        let _ = thread.join();
+    }
+}
@@ -0,0 +1,99 @@
+use crate::activation::{Activation, Mode};
+use crate::overlay::Canvas;
+use livesplit_core::component::text::{self, Text};
+use livesplit_core::layout::{Layout, LayoutState};
+use livesplit_core::rendering::software::Renderer;
+use livesplit_core::settings::{Color, Gradient};
+use livesplit_core::Timer;
+
+/// How many bindings are listed at a time. The layout is scaled to fit the
+/// window, so this keeps the text readable.
+const ROWS: usize = 12;
+
+/// A binding in effect, as listed by the help.
+pub struct Entry {
+    /// The setting in `hotkeys` or `keys`.
+    pub name: String,
+    /// The key, chord or mouse input.
+    pub input: String,
+    /// Whether it works in other windows too.
+    pub global: bool,
+    pub activation: Activation,
+}
+
+impl Entry {
+    fn describe(&self) -> String {
+        let mut notes = vec![if self.global { "global" } else { "window" }];
+        match self.activation.mode {
+            Mode::Press => {}
+            Mode::Hold => notes.push("hold"),
+            Mode::DoubleTap => notes.push("double-tap"),
+            Mode::Confirm => notes.push("confirm"),
+        }
+        format!("{} ({})", self.input, notes.join(", "))
+    }
+}
+
+/// The bindings listed over the layout, a page at a time, see
+/// `Action::Help`.
+pub struct Help {
+    page: usize,
+    pages: usize,
+    layout: Layout,
+    state: LayoutState,
+    renderer: Renderer,
+}
+
+impl Help {
+    pub fn new(entries: &[Entry]) -> Self {
+        let pages = entries.len().div_ceil(ROWS).max(1);
+        Self {
+            page: 0,
+            pages,
+            layout: page_layout(entries, 0, pages),
+            state: LayoutState::default(),
+            renderer: Renderer::new(),
+        }
+    }
+
+    /// Turns to the next page, returning false after the last one.
+    pub fn next_page(&mut self, entries: &[Entry]) -> bool {
+        if self.page + 1 >= self.pages {
+            return false;
+        }
+        self.page += 1;
+        self.layout = page_layout(entries, self.page, self.pages);
+        true
+    }
+
+    pub fn title(&self) -> String {
+        format!("Bindings {}/{}", self.page + 1, self.pages)
+    }
+
+    /// Renders the page and draws it over what is on the canvas.
+    pub fn draw(&mut self, timer: &Timer, canvas: &mut Canvas<'_>) {
+        let (width, height) = canvas.size();
+        self.layout.update_state(&mut self.state, &timer.snapshot());
+        self.renderer
+            .render(&self.state, [width as u32, height as u32]);
+        canvas.blend(self.renderer.image_data());
+    }
+}
+
+fn page_layout(entries: &[Entry], page: usize, pages: usize) -> Layout {
+    let mut layout = Layout::new();
+    layout.general_settings_mut().background = Gradient::Plain(Color::rgba(0.0, 0.0, 0.0, 0.85));
+    let heading = format!("Bindings {}/{}", page + 1, pages);
+    layout.push(text_row(Text::Center(heading)));
+    for entry in entries.iter().skip(page * ROWS).take(ROWS) {
+        layout.push(text_row(Text::Split(entry.name.clone(), entry.describe())));
+    }
+    layout
+}
+
+fn text_row(text: Text) -> text::Component {
+    text::Component::with_settings(text::Settings {
+        text,
+        ..Default::default()
+    })
+}
@@ -0,0 +1,35 @@
+use crate::events::TimerEvent;
+use std::process::Command;
+
+/// Runs the command set in `hooks` for the action of the event, like
+/// `Split: notify-send split`, through the shell. The event is passed in the
+/// `LIVESPLIT_*` environment variables. Commands run one at a time, in the
+/// order of the events.
+pub fn run(event: &TimerEvent) {
+    let command = match crate::config().hook(&event.action) {
+        Some(command) => command.to_owned(),
+        None => return,
+    };
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    let status = shell
+        .arg(&command)
+        .env("LIVESPLIT_ACTION", &event.action)
+        .env("LIVESPLIT_PHASE", event.phase.to_string())
+        .env("LIVESPLIT_SPLIT", &event.split_name)
+        .env("LIVESPLIT_ATTEMPT", event.attempt.to_string())
+        .env("LIVESPLIT_RUN", &event.run_name)
+        .status();
+    match status {
+        Ok(status) if status.success() => {}
+        Ok(status) => log::warn!("Hook `{}` failed: {}", command, status),
+        Err(e) => log::error!("Couldn't run hook `{}`: {}", command, e),
+    }
+}
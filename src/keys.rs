@@ -1,15 +1,42 @@
 use livesplit_core::hotkey::Hotkey;
-use serde::Deserialize;
-use std::str::FromStr;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::{fmt, str::FromStr};
 use winit::event::ModifiersState;
 use winit::event::VirtualKeyCode;
 
 #[derive(Debug, PartialEq, Eq)]
-pub struct ParseKeyError;
+pub struct ParseKeyError {
+    name: String,
+}
+
+impl fmt::Display for ParseKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown key `{}`", self.name)
+    }
+}
+
+impl std::error::Error for ParseKeyError {}
 
-#[derive(Debug, Hash, PartialEq, Eq, Deserialize, Copy, Clone)]
+impl ParseKeyError {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+        }
+    }
+}
+
+/// What a key is told apart by: the key it types, which depends on the
+/// keyboard layout, or where it is on the keyboard.
+#[derive(Debug, Hash, PartialEq, Eq, Copy, Clone)]
+pub enum Code {
+    Virtual(VirtualKeyCode),
+    /// Written as `Scan<n>`, like `Scan30`.
+    Scan(u32),
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Copy, Clone)]
 pub struct Key {
-    pub key: VirtualKeyCode,
+    pub key: Code,
     pub mods: ModifiersState,
 }
 
@@ -30,17 +57,39 @@ impl Key {
         Self::from(key, ModifiersState::ALT)
     }
     pub fn from(key: VirtualKeyCode, mods: ModifiersState) -> Self {
-        Self { key, mods }
+        Self {
+            key: Code::Virtual(key),
+            mods,
+        }
     }
-}
-impl From<&Hotkey> for Key {
-    fn from(s: &Hotkey) -> Self {
-        (*s).into()
+    pub fn scan(scancode: u32, mods: ModifiersState) -> Self {
+        Self {
+            key: Code::Scan(scancode),
+            mods,
+        }
+    }
+    /// Whether this is a modifier key on its own, like `ControlLeft`.
+    pub fn is_modifier(&self) -> bool {
+        matches!(
+            self.key,
+            Code::Virtual(
+                VirtualKeyCode::LShift
+                    | VirtualKeyCode::RShift
+                    | VirtualKeyCode::LControl
+                    | VirtualKeyCode::RControl
+                    | VirtualKeyCode::LAlt
+                    | VirtualKeyCode::RAlt
+                    | VirtualKeyCode::LWin
+                    | VirtualKeyCode::RWin
+            )
+        )
     }
 }
 
-impl From<Hotkey> for Key {
-    fn from(s: Hotkey) -> Self {
+impl Key {
+    /// Converts a livesplit-core hotkey, failing for keys that have no
+    /// matching winit key code.
+    pub fn from_hotkey(s: Hotkey) -> Result<Self, ParseKeyError> {
         let mut mods = ModifiersState::empty();
         if s.modifiers
             .intersects(livesplit_core::hotkey::Modifiers::SHIFT)
@@ -63,33 +112,46 @@ impl From<Hotkey> for Key {
             mods |= ModifiersState::LOGO;
         }
         let name = s.key_code.name();
-        let key = str_to_virtual_key_code(name).unwrap();
-        Key::from(key, mods)
+        let key = str_to_virtual_key_code(name)?;
+        Ok(Key::from(key, mods))
     }
 }
 
-fn str_to_mods(s: &str) -> Result<ModifiersState, ParseKeyError> {
+/// The modifiers in the order they are written in.
+const MODIFIER_NAMES: [(&str, ModifiersState); 4] = [
+    ("Ctrl", ModifiersState::CTRL),
+    ("Alt", ModifiersState::ALT),
+    ("Meta", ModifiersState::LOGO),
+    ("Shift", ModifiersState::SHIFT),
+];
+
+pub fn str_to_mods(s: &str) -> Result<ModifiersState, ParseKeyError> {
     let mut modifiers = ModifiersState::empty();
     for modifier in s.split('+').map(str::trim) {
-        match modifier {
-            "Ctrl" => modifiers.insert(ModifiersState::CTRL),
-            "Alt" => modifiers.insert(ModifiersState::ALT),
-            "Meta" => modifiers.insert(ModifiersState::LOGO),
-            "Shift" => modifiers.insert(ModifiersState::SHIFT),
-            _ => return Err(ParseKeyError),
-        }
+        let (_, state) = MODIFIER_NAMES
+            .iter()
+            .find(|(name, _)| *name == modifier)
+            .ok_or_else(|| ParseKeyError::new(modifier))?;
+        modifiers.insert(*state);
     }
     Ok(modifiers)
 }
+fn str_to_code(s: &str) -> Result<Code, ParseKeyError> {
+    if let Some(scancode) = s.strip_prefix("Scan") {
+        let scancode = scancode.parse().map_err(|_| ParseKeyError::new(s))?;
+        return Ok(Code::Scan(scancode));
+    }
+    str_to_virtual_key_code(s).map(Code::Virtual)
+}
 impl FromStr for Key {
     type Err = ParseKeyError;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         if let Some((modifiers, key_code)) = s.rsplit_once('+') {
             let mods = str_to_mods(modifiers.trim())?;
-            let key = str_to_virtual_key_code(key_code.trim())?;
+            let key = str_to_code(key_code.trim())?;
             Ok(Self { key, mods })
         } else {
-            let key = str_to_virtual_key_code(s)?;
+            let key = str_to_code(s)?;
             Ok(Self {
                 key,
                 mods: ModifiersState::empty(),
@@ -98,113 +160,315 @@ impl FromStr for Key {
     }
 }
 
-fn str_to_virtual_key_code(s: &str) -> Result<VirtualKeyCode, ParseKeyError> {
-    let k = match s {
-        "Digit0" | "0" => VirtualKeyCode::Key0,
-        "Digit1" | "1" => VirtualKeyCode::Key1,
-        "Digit2" | "2" => VirtualKeyCode::Key2,
-        "Digit3" | "3" => VirtualKeyCode::Key3,
-        "Digit4" | "4" => VirtualKeyCode::Key4,
-        "Digit5" | "5" => VirtualKeyCode::Key5,
-        "Digit6" | "6" => VirtualKeyCode::Key6,
-        "Digit7" | "7" => VirtualKeyCode::Key7,
-        "Digit8" | "8" => VirtualKeyCode::Key8,
-        "Digit9" | "9" => VirtualKeyCode::Key9,
-        "Numpad0" => VirtualKeyCode::Numpad0,
-        "Numpad1" => VirtualKeyCode::Numpad1,
-        "Numpad2" => VirtualKeyCode::Numpad2,
-        "Numpad3" => VirtualKeyCode::Numpad3,
-        "Numpad4" => VirtualKeyCode::Numpad4,
-        "Numpad5" => VirtualKeyCode::Numpad5,
-        "Numpad6" => VirtualKeyCode::Numpad6,
-        "Numpad7" => VirtualKeyCode::Numpad7,
-        "Numpad8" => VirtualKeyCode::Numpad8,
-        "Numpad9" => VirtualKeyCode::Numpad9,
-        "KeyA" | "A" => VirtualKeyCode::A,
-        "KeyB" | "B" => VirtualKeyCode::B,
-        "KeyC" | "C" => VirtualKeyCode::C,
-        "KeyD" | "D" => VirtualKeyCode::D,
-        "KeyE" | "E" => VirtualKeyCode::E,
-        "KeyF" | "F" => VirtualKeyCode::F,
-        "KeyG" | "G" => VirtualKeyCode::G,
-        "KeyH" | "H" => VirtualKeyCode::H,
-        "KeyI" | "I" => VirtualKeyCode::I,
-        "KeyJ" | "J" => VirtualKeyCode::J,
-        "KeyK" | "K" => VirtualKeyCode::K,
-        "KeyL" | "L" => VirtualKeyCode::L,
-        "KeyM" | "M" => VirtualKeyCode::M,
-        "KeyN" | "N" => VirtualKeyCode::N,
-        "KeyO" | "O" => VirtualKeyCode::O,
-        "KeyP" | "P" => VirtualKeyCode::P,
-        "KeyQ" | "Q" => VirtualKeyCode::Q,
-        "KeyR" | "R" => VirtualKeyCode::R,
-        "KeyS" | "S" => VirtualKeyCode::S,
-        "KeyT" | "T" => VirtualKeyCode::T,
-        "KeyU" | "U" => VirtualKeyCode::U,
-        "KeyV" | "V" => VirtualKeyCode::V,
-        "KeyW" | "W" => VirtualKeyCode::W,
-        "KeyX" | "X" => VirtualKeyCode::X,
-        "KeyY" | "Y" => VirtualKeyCode::Y,
-        "KeyZ" | "Z" => VirtualKeyCode::Z,
-        "Backslash" => VirtualKeyCode::Backslash,
-        "BracketLeft" => VirtualKeyCode::LBracket,
-        "BracketRight" => VirtualKeyCode::RBracket,
-        "Comma" => VirtualKeyCode::Comma,
-        "Minus" => VirtualKeyCode::Minus,
-        "Period" => VirtualKeyCode::Period,
-        "Quote" => VirtualKeyCode::Apostrophe,
-        "Semicolon" => VirtualKeyCode::Semicolon,
-        "Slash" => VirtualKeyCode::Slash,
-        "AltLeft" => VirtualKeyCode::LAlt,
-        "AltRight" => VirtualKeyCode::RAlt,
-        "Backspace" => VirtualKeyCode::Back,
-        "ControlLeft" => VirtualKeyCode::LControl,
-        "ControlRight" => VirtualKeyCode::RControl,
-        "Enter" => VirtualKeyCode::Return,
-        "MetaLeft" => VirtualKeyCode::LWin,
-        "MetaRight" => VirtualKeyCode::RWin,
-        "ShiftLeft" => VirtualKeyCode::LShift,
-        "ShiftRight" => VirtualKeyCode::RShift,
-        "Space" => VirtualKeyCode::Space,
-        "Tab" => VirtualKeyCode::Tab,
-        "Convert" => VirtualKeyCode::Convert,
-        "Delete" => VirtualKeyCode::Delete,
-        "End" => VirtualKeyCode::End,
-        "Home" => VirtualKeyCode::Home,
-        "Insert" => VirtualKeyCode::Insert,
-        "PageDown" => VirtualKeyCode::PageDown,
-        "PageUp" => VirtualKeyCode::PageUp,
-        "ArrowDown" => VirtualKeyCode::Down,
-        "ArrowUp" => VirtualKeyCode::Up,
-        "ArrowLeft" => VirtualKeyCode::Left,
-        "ArrowRight" => VirtualKeyCode::Right,
-        "NumLock" => VirtualKeyCode::Numlock,
-        "NumpadAdd" => VirtualKeyCode::NumpadAdd,
-        "NumpadComma" => VirtualKeyCode::NumpadComma,
-        "NumpadDecimal" => VirtualKeyCode::NumpadDecimal,
-        "NumpadDivide" => VirtualKeyCode::NumpadDivide,
-        "NumpadEnter" => VirtualKeyCode::NumpadEnter,
-        "NumpadEqual" => VirtualKeyCode::NumpadEquals,
-        "NumpadMultiply" => VirtualKeyCode::NumpadMultiply,
-        "NumpadSubstract" => VirtualKeyCode::NumpadSubtract,
-        "Escape" => VirtualKeyCode::Escape,
-        //"Backquote" => VirtualKeyCode::???,
-        //"CapsLock" => VirtualKeyCode::,
-        //"ContextMenu" => VirtualKeyCode::,
-        //"Help" => VirtualKeyCode::Help,
-        //"NumpadBackspace" => VirtualKeyCode::,
-        //"NumpadClear" => VirtualKeyCode::,
-        //"NumpadClearEntry" => VirtualKeyCode::,
-        //"NumpadHash" => VirtualKeyCode::,
-        //"NumparParenLeft" => VirtualKeyCode::,
-        //"NumpadParenRight" => VirtualKeyCode::,
-        //"NumpadStar" => VirtualKeyCode::,
-        _ => {
-            dbg!("unimplemented", s);
-            return Err(ParseKeyError);
+/// Parses a sequence of keys separated by spaces, like `Ctrl+K R`.
+pub fn parse_sequence(s: &str) -> Result<Vec<Key>, ParseKeyError> {
+    s.split_whitespace().map(str::parse).collect()
+}
+
+/// Writes the modifiers the way `str_to_mods` reads them, each followed by `+`.
+pub fn write_mods(f: &mut fmt::Formatter<'_>, mods: ModifiersState) -> fmt::Result {
+    for (name, state) in MODIFIER_NAMES {
+        if mods.contains(state) {
+            write!(f, "{}+", name)?;
         }
-    };
-    Ok(k)
+    }
+    Ok(())
+}
+
+/// Writes the key the way `FromStr` reads it, like `Ctrl+Shift+KeyA`.
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_mods(f, self.mods)?;
+        match self.key {
+            Code::Virtual(key) => f.write_str(virtual_key_code_to_str(key)),
+            Code::Scan(scancode) => write!(f, "Scan{}", scancode),
+        }
+    }
+}
+
+/// A key as set in the `hotkeys` and `keys` sections: a livesplit-core hotkey,
+/// which can be registered globally, or a scancode, which is the same key on
+/// every keyboard layout but only works in the window.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Binding {
+    Hotkey(Hotkey),
+    Scan(Key),
+}
+
+impl Binding {
+    pub fn hotkey(&self) -> Option<Hotkey> {
+        match self {
+            Binding::Hotkey(hotkey) => Some(*hotkey),
+            Binding::Scan(_) => None,
+        }
+    }
+    /// The key to look for in the window.
+    pub fn key(&self) -> Result<Key, ParseKeyError> {
+        match self {
+            Binding::Hotkey(hotkey) => Key::from_hotkey(*hotkey),
+            Binding::Scan(key) => Ok(*key),
+        }
+    }
+}
+
+impl FromStr for Binding {
+    type Err = ParseKeyError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.parse::<Key>() {
+            Ok(key) if matches!(key.key, Code::Scan(_)) => Ok(Binding::Scan(key)),
+            _ => s
+                .parse()
+                .map(Binding::Hotkey)
+                .map_err(|_| ParseKeyError::new(s)),
+        }
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Binding::Hotkey(hotkey) => hotkey.fmt(f),
+            Binding::Scan(key) => key.fmt(f),
+        }
+    }
+}
+
+impl Serialize for Binding {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Binding::Hotkey(hotkey) => hotkey.serialize(serializer),
+            Binding::Scan(key) => serializer.collect_str(key),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Binding {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+/// The names of the keys, the ones of livesplit-core where there is one. A
+/// key is written with its first name, the others are accepted too.
+const KEY_NAMES: &[(&str, VirtualKeyCode)] = &[
+    ("Digit0", VirtualKeyCode::Key0),
+    ("Digit1", VirtualKeyCode::Key1),
+    ("Digit2", VirtualKeyCode::Key2),
+    ("Digit3", VirtualKeyCode::Key3),
+    ("Digit4", VirtualKeyCode::Key4),
+    ("Digit5", VirtualKeyCode::Key5),
+    ("Digit6", VirtualKeyCode::Key6),
+    ("Digit7", VirtualKeyCode::Key7),
+    ("Digit8", VirtualKeyCode::Key8),
+    ("Digit9", VirtualKeyCode::Key9),
+    ("0", VirtualKeyCode::Key0),
+    ("1", VirtualKeyCode::Key1),
+    ("2", VirtualKeyCode::Key2),
+    ("3", VirtualKeyCode::Key3),
+    ("4", VirtualKeyCode::Key4),
+    ("5", VirtualKeyCode::Key5),
+    ("6", VirtualKeyCode::Key6),
+    ("7", VirtualKeyCode::Key7),
+    ("8", VirtualKeyCode::Key8),
+    ("9", VirtualKeyCode::Key9),
+    ("KeyA", VirtualKeyCode::A),
+    ("KeyB", VirtualKeyCode::B),
+    ("KeyC", VirtualKeyCode::C),
+    ("KeyD", VirtualKeyCode::D),
+    ("KeyE", VirtualKeyCode::E),
+    ("KeyF", VirtualKeyCode::F),
+    ("KeyG", VirtualKeyCode::G),
+    ("KeyH", VirtualKeyCode::H),
+    ("KeyI", VirtualKeyCode::I),
+    ("KeyJ", VirtualKeyCode::J),
+    ("KeyK", VirtualKeyCode::K),
+    ("KeyL", VirtualKeyCode::L),
+    ("KeyM", VirtualKeyCode::M),
+    ("KeyN", VirtualKeyCode::N),
+    ("KeyO", VirtualKeyCode::O),
+    ("KeyP", VirtualKeyCode::P),
+    ("KeyQ", VirtualKeyCode::Q),
+    ("KeyR", VirtualKeyCode::R),
+    ("KeyS", VirtualKeyCode::S),
+    ("KeyT", VirtualKeyCode::T),
+    ("KeyU", VirtualKeyCode::U),
+    ("KeyV", VirtualKeyCode::V),
+    ("KeyW", VirtualKeyCode::W),
+    ("KeyX", VirtualKeyCode::X),
+    ("KeyY", VirtualKeyCode::Y),
+    ("KeyZ", VirtualKeyCode::Z),
+    ("A", VirtualKeyCode::A),
+    ("B", VirtualKeyCode::B),
+    ("C", VirtualKeyCode::C),
+    ("D", VirtualKeyCode::D),
+    ("E", VirtualKeyCode::E),
+    ("F", VirtualKeyCode::F),
+    ("G", VirtualKeyCode::G),
+    ("H", VirtualKeyCode::H),
+    ("I", VirtualKeyCode::I),
+    ("J", VirtualKeyCode::J),
+    ("K", VirtualKeyCode::K),
+    ("L", VirtualKeyCode::L),
+    ("M", VirtualKeyCode::M),
+    ("N", VirtualKeyCode::N),
+    ("O", VirtualKeyCode::O),
+    ("P", VirtualKeyCode::P),
+    ("Q", VirtualKeyCode::Q),
+    ("R", VirtualKeyCode::R),
+    ("S", VirtualKeyCode::S),
+    ("T", VirtualKeyCode::T),
+    ("U", VirtualKeyCode::U),
+    ("V", VirtualKeyCode::V),
+    ("W", VirtualKeyCode::W),
+    ("X", VirtualKeyCode::X),
+    ("Y", VirtualKeyCode::Y),
+    ("Z", VirtualKeyCode::Z),
+    ("F1", VirtualKeyCode::F1),
+    ("F2", VirtualKeyCode::F2),
+    ("F3", VirtualKeyCode::F3),
+    ("F4", VirtualKeyCode::F4),
+    ("F5", VirtualKeyCode::F5),
+    ("F6", VirtualKeyCode::F6),
+    ("F7", VirtualKeyCode::F7),
+    ("F8", VirtualKeyCode::F8),
+    ("F9", VirtualKeyCode::F9),
+    ("F10", VirtualKeyCode::F10),
+    ("F11", VirtualKeyCode::F11),
+    ("F12", VirtualKeyCode::F12),
+    ("F13", VirtualKeyCode::F13),
+    ("F14", VirtualKeyCode::F14),
+    ("F15", VirtualKeyCode::F15),
+    ("F16", VirtualKeyCode::F16),
+    ("F17", VirtualKeyCode::F17),
+    ("F18", VirtualKeyCode::F18),
+    ("F19", VirtualKeyCode::F19),
+    ("F20", VirtualKeyCode::F20),
+    ("F21", VirtualKeyCode::F21),
+    ("F22", VirtualKeyCode::F22),
+    ("F23", VirtualKeyCode::F23),
+    ("F24", VirtualKeyCode::F24),
+    ("Numpad0", VirtualKeyCode::Numpad0),
+    ("Numpad1", VirtualKeyCode::Numpad1),
+    ("Numpad2", VirtualKeyCode::Numpad2),
+    ("Numpad3", VirtualKeyCode::Numpad3),
+    ("Numpad4", VirtualKeyCode::Numpad4),
+    ("Numpad5", VirtualKeyCode::Numpad5),
+    ("Numpad6", VirtualKeyCode::Numpad6),
+    ("Numpad7", VirtualKeyCode::Numpad7),
+    ("Numpad8", VirtualKeyCode::Numpad8),
+    ("Numpad9", VirtualKeyCode::Numpad9),
+    ("Backquote", VirtualKeyCode::Grave),
+    ("Backslash", VirtualKeyCode::Backslash),
+    ("BracketLeft", VirtualKeyCode::LBracket),
+    ("BracketRight", VirtualKeyCode::RBracket),
+    ("Comma", VirtualKeyCode::Comma),
+    ("Equal", VirtualKeyCode::Equals),
+    ("IntlBackslash", VirtualKeyCode::OEM102),
+    ("IntlRo", VirtualKeyCode::AbntC1),
+    ("IntlYen", VirtualKeyCode::Yen),
+    ("Minus", VirtualKeyCode::Minus),
+    ("Period", VirtualKeyCode::Period),
+    ("Quote", VirtualKeyCode::Apostrophe),
+    ("Semicolon", VirtualKeyCode::Semicolon),
+    ("Slash", VirtualKeyCode::Slash),
+    ("AltLeft", VirtualKeyCode::LAlt),
+    ("AltRight", VirtualKeyCode::RAlt),
+    ("Backspace", VirtualKeyCode::Back),
+    ("CapsLock", VirtualKeyCode::Capital),
+    ("ContextMenu", VirtualKeyCode::Apps),
+    ("ControlLeft", VirtualKeyCode::LControl),
+    ("ControlRight", VirtualKeyCode::RControl),
+    ("Enter", VirtualKeyCode::Return),
+    ("MetaLeft", VirtualKeyCode::LWin),
+    ("MetaRight", VirtualKeyCode::RWin),
+    ("ShiftLeft", VirtualKeyCode::LShift),
+    ("ShiftRight", VirtualKeyCode::RShift),
+    ("Space", VirtualKeyCode::Space),
+    ("Tab", VirtualKeyCode::Tab),
+    ("Convert", VirtualKeyCode::Convert),
+    ("NonConvert", VirtualKeyCode::NoConvert),
+    ("KanaMode", VirtualKeyCode::Kana),
+    ("Kanji", VirtualKeyCode::Kanji),
+    ("Delete", VirtualKeyCode::Delete),
+    ("End", VirtualKeyCode::End),
+    ("Home", VirtualKeyCode::Home),
+    ("Insert", VirtualKeyCode::Insert),
+    ("PageDown", VirtualKeyCode::PageDown),
+    ("PageUp", VirtualKeyCode::PageUp),
+    ("ArrowDown", VirtualKeyCode::Down),
+    ("ArrowLeft", VirtualKeyCode::Left),
+    ("ArrowRight", VirtualKeyCode::Right),
+    ("ArrowUp", VirtualKeyCode::Up),
+    ("NumLock", VirtualKeyCode::Numlock),
+    ("NumpadAdd", VirtualKeyCode::NumpadAdd),
+    ("NumpadComma", VirtualKeyCode::NumpadComma),
+    ("NumpadDecimal", VirtualKeyCode::NumpadDecimal),
+    ("NumpadDivide", VirtualKeyCode::NumpadDivide),
+    ("NumpadEnter", VirtualKeyCode::NumpadEnter),
+    ("NumpadEqual", VirtualKeyCode::NumpadEquals),
+    ("NumpadMultiply", VirtualKeyCode::NumpadMultiply),
+    ("NumpadSubtract", VirtualKeyCode::NumpadSubtract),
+    // Older builds wrote this misspelling.
+    ("NumpadSubstract", VirtualKeyCode::NumpadSubtract),
+    ("Escape", VirtualKeyCode::Escape),
+    ("PrintScreen", VirtualKeyCode::Snapshot),
+    ("ScrollLock", VirtualKeyCode::Scroll),
+    ("Pause", VirtualKeyCode::Pause),
+    ("BrowserBack", VirtualKeyCode::WebBack),
+    ("BrowserFavorites", VirtualKeyCode::WebFavorites),
+    ("BrowserForward", VirtualKeyCode::WebForward),
+    ("BrowserHome", VirtualKeyCode::WebHome),
+    ("BrowserRefresh", VirtualKeyCode::WebRefresh),
+    ("BrowserSearch", VirtualKeyCode::WebSearch),
+    ("BrowserStop", VirtualKeyCode::WebStop),
+    ("LaunchApp1", VirtualKeyCode::MyComputer),
+    ("LaunchApp2", VirtualKeyCode::Calculator),
+    ("LaunchMail", VirtualKeyCode::Mail),
+    ("MediaPlayPause", VirtualKeyCode::PlayPause),
+    ("MediaSelect", VirtualKeyCode::MediaSelect),
+    ("MediaStop", VirtualKeyCode::MediaStop),
+    ("MediaTrackNext", VirtualKeyCode::NextTrack),
+    ("MediaTrackPrevious", VirtualKeyCode::PrevTrack),
+    ("AudioVolumeDown", VirtualKeyCode::VolumeDown),
+    ("AudioVolumeMute", VirtualKeyCode::Mute),
+    ("AudioVolumeUp", VirtualKeyCode::VolumeUp),
+    ("Power", VirtualKeyCode::Power),
+    ("Sleep", VirtualKeyCode::Sleep),
+    ("WakeUp", VirtualKeyCode::Wake),
+    ("Copy", VirtualKeyCode::Copy),
+    ("Cut", VirtualKeyCode::Cut),
+    ("Paste", VirtualKeyCode::Paste),
+    ("NavigateBackward", VirtualKeyCode::NavigateBackward),
+    ("NavigateForward", VirtualKeyCode::NavigateForward),
+    ("AbntC2", VirtualKeyCode::AbntC2),
+    ("Asterisk", VirtualKeyCode::Asterisk),
+    ("At", VirtualKeyCode::At),
+    ("Ax", VirtualKeyCode::Ax),
+    ("Caret", VirtualKeyCode::Caret),
+    ("Colon", VirtualKeyCode::Colon),
+    ("Compose", VirtualKeyCode::Compose),
+    ("Plus", VirtualKeyCode::Plus),
+    ("Stop", VirtualKeyCode::Stop),
+    ("Sysrq", VirtualKeyCode::Sysrq),
+    ("Underline", VirtualKeyCode::Underline),
+    ("Unlabeled", VirtualKeyCode::Unlabeled),
+];
+
+fn str_to_virtual_key_code(s: &str) -> Result<VirtualKeyCode, ParseKeyError> {
+    KEY_NAMES
+        .iter()
+        .find(|(name, _)| *name == s)
+        .map(|(_, key)| *key)
+        .ok_or_else(|| ParseKeyError::new(s))
+}
+
+fn virtual_key_code_to_str(key: VirtualKeyCode) -> &'static str {
+    KEY_NAMES
+        .iter()
+        .find(|(_, k)| *k == key)
+        .map_or("Unidentified", |(name, _)| *name)
 }
 /*
 impl From<Hotkey> for FBKeyCode {
@@ -370,3 +634,22 @@ impl FBKeyCode {
     }
 }
 */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_key_name_round_trips() {
+        let modifiers = [
+            ModifiersState::empty(),
+            ModifiersState::CTRL | ModifiersState::SHIFT,
+        ];
+        for (name, code) in KEY_NAMES {
+            for mods in modifiers {
+                let key = Key::from(*code, mods);
+                assert_eq!(key.to_string().parse(), Ok(key), "`{}`", name);
+            }
+        }
+    }
+}
@@ -1,35 +1,59 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod activation;
+mod bind;
 mod config;
+mod defaults;
+mod events;
+mod help;
+mod hooks;
 pub mod keys;
+mod migrate;
+mod mouse;
 mod stream_markers;
 
 mod notifier;
+mod overlay;
+mod overrides;
+mod paths;
+mod persist;
+mod sidecar;
+use activation::{Activation, Activator, Mode};
+use bind::{Binder, Outcome};
+use help::Help;
+use mouse::{Mouse, Wheel};
 use notifier::Notifier;
+use overlay::Canvas;
+use overrides::{Override, Source};
 
-use config::Config;
-use keys::Key;
+use config::{Config, Profile};
+use events::{Subscription, TimerEvent};
+use keys::{Binding, Code, Key};
 
 use bytemuck::{Pod, Zeroable};
 use clap::Parser;
 use lazy_static::lazy_static;
+use livesplit_core::hotkey::Hook;
 use livesplit_core::layout;
 use livesplit_core::layout::LayoutSettings;
 use livesplit_core::{auto_splitting, rendering::software::Renderer};
 use livesplit_core::{layout::Layout, layout::LayoutState};
-use livesplit_core::{SharedTimer, Timer};
+use livesplit_core::{HotkeySystem, SharedTimer, Timer, TimerPhase};
 use rfd::FileDialog;
-use std::collections::HashMap;
+use serde_yaml::Value;
+use std::collections::{HashMap, HashSet};
 use std::io::Cursor;
-use std::path::PathBuf;
-use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::time::Instant;
 use winit::event::ElementState;
 use winit::event::KeyboardInput;
 use winit::event::ModifiersState;
+use winit::event::MouseButton;
 use winit::event::MouseScrollDelta;
-use winit::event::VirtualKeyCode;
 use winit::event::{Event, WindowEvent};
-use winit::event_loop::EventLoop;
+use winit::event_loop::{EventLoopBuilder, EventLoopProxy};
 
 lazy_static! {
     static ref CONFIG: RwLock<Config> = RwLock::new(Config::new());
@@ -38,14 +62,77 @@ lazy_static! {
     static ref NOTIFIER: RwLock<Notifier> = RwLock::new(Notifier::new());
 }
 
+lazy_static! {
+    /// Lets the global hotkeys hand their actions to the event loop.
+    static ref PROXY: Mutex<Option<EventLoopProxy<UserEvent>>> = Mutex::new(None);
+}
+
+/// Whether all bindings but the one for `Action::ToggleLock` are off, so
+/// typing elsewhere doesn't control the timer.
+static LOCKED: AtomicBool = AtomicBool::new(false);
+
 struct WTimer {
     timer: SharedTimer,
-    markers: stream_markers::Client,
+    _markers: Option<Subscription>,
     layout: Layout,
     keys: HashMap<Key, Action>,
+    mouse: HashMap<Mouse, Action>,
+    /// Key sequences, only the last key triggers the action.
+    chords: HashMap<Vec<Key>, Action>,
+    /// The keys typed so far of a chord, and when the last one was.
+    pending: Vec<Key>,
+    pending_since: Instant,
+    /// How each action is triggered, if not by a single press.
+    activations: HashMap<Action, Activation>,
+    activator: Activator<Action>,
+    /// When each action last ran, for the cooldowns.
+    last_run: HashMap<Action, Instant>,
+    /// The scancodes of the keys that are down, to tell the OS auto-repeat
+    /// from new presses, and whether their auto-repeat was logged.
+    held: HashMap<u32, bool>,
+    /// Set while binding keys, which takes all the keys pressed.
+    binder: Option<Binder>,
+    /// The bindings in effect, as listed by the help.
+    listed: Vec<help::Entry>,
+    /// Set while the help is shown.
+    help: Option<Help>,
+    /// Set by the quit action, the event loop exits once it sees it.
+    quit: bool,
+    /// The global hotkeys stay registered for as long as these live.
+    _hotkey_system: Option<HotkeySystem>,
+    _app_hotkeys: Option<Hook>,
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug)]
+enum UserEvent {
+    ConfigChanged,
+    /// A global hotkey for one of the app's actions was pressed.
+    Action(Action),
+}
+
+/// The frame around the window while the bindings are locked.
+const LOCK_COLOR: u32 = 0xFF_CC_33_33;
+/// The bar showing that keys are being bound.
+const BIND_COLOR: u32 = 0xFF_33_CC_66;
+/// The bar showing the time left to finish a chord.
+const CHORD_COLOR: u32 = 0xFF_33_99_FF;
+/// The bar filling up while a key is held.
+const HOLD_COLOR: u32 = 0xFF_FF_99_00;
+/// The bar showing the time left for a second tap or the confirmation.
+const CONFIRM_COLOR: u32 = 0xFF_FF_33_33;
+
+fn chord_name(chord: &[Key]) -> String {
+    let keys: Vec<String> = chord.iter().map(Key::to_string).collect();
+    keys.join(" ")
+}
+
+fn send_user_event(event: UserEvent) {
+    if let Some(proxy) = &*PROXY.lock().unwrap() {
+        let _ = proxy.send_event(event);
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 enum Action {
     Split,
     Reset,
@@ -63,18 +150,96 @@ enum Action {
     Quit,
     LayoutUp,
     LayoutDown,
+    NextProfile,
+    /// Opens the n-th most recent splits file, counting from 0.
+    RecentSplits(usize),
+    RecentLayout(usize),
+    /// Opens the least recently used splits file, which cycles through all
+    /// of them when repeated.
+    NextSplits,
+    NextLayout,
+    /// Binds keys from within the window.
+    Bind,
+    /// Turns all other bindings off or back on.
+    ToggleLock,
+    /// Lists the bindings over the layout, a page at a time.
+    Help,
 }
 
-pub fn save_state(timer_state: &livesplit_core::TimerState) {
-    let path = config().state_file();
-    std::fs::write(&path, timer_state.to_json()).unwrap();
+impl Action {
+    /// The action bound by the setting `name` in the `hotkeys` or `keys`
+    /// section.
+    fn from_name(name: &str) -> Option<Action> {
+        let recent = |prefix: &str| -> Option<usize> {
+            let n: usize = name.strip_prefix(prefix)?.parse().ok()?;
+            n.checked_sub(1)
+        };
+        Some(match name {
+            "split" => Action::Split,
+            "reset" => Action::Reset,
+            "undo" => Action::Undo,
+            "skip" => Action::Skip,
+            "pause" => Action::Pause,
+            "undo_all_pauses" => Action::UndoAllPauses,
+            "previous_comparison" => Action::PreviousComparison,
+            "next_comparison" => Action::NextComparison,
+            "toggle_timing_method" => Action::ToggleTimingMethod,
+            "open" => Action::OpenSplits,
+            "save" => Action::SaveSplits,
+            "quit" => Action::Quit,
+            "hide" => Action::HideComparison,
+            "layout" => Action::OpenLayout,
+            "layout_up" => Action::LayoutUp,
+            "layout_down" => Action::LayoutDown,
+            "profile" => Action::NextProfile,
+            "next_splits" => Action::NextSplits,
+            "next_layout" => Action::NextLayout,
+            "bind" => Action::Bind,
+            "lock" => Action::ToggleLock,
+            "help" => Action::Help,
+            _ => {
+                if let Some(n) = recent("recent_splits_") {
+                    Action::RecentSplits(n)
+                } else {
+                    Action::RecentLayout(recent("recent_layout_")?)
+                }
+            }
+        })
+    }
+}
+
+/// Saves the timer state after every change. The state file is only rotated
+/// into the backups the first time it is saved this session and when an
+/// attempt ends, so the backups don't fill up with the splits of one attempt.
+fn state_saver() -> impl FnMut(&TimerEvent) + Send {
+    let mut rotated = HashSet::new();
+    let mut phase = TimerPhase::NotRunning;
+    move |event| {
+        let ended = phase != TimerPhase::NotRunning && event.phase == TimerPhase::NotRunning;
+        phase = event.phase;
+        let config = config();
+        let rotate = rotated.insert(config.state_file()) | ended;
+        config.save_state(&event.state, rotate);
+    }
+}
+
+fn notify_timer_change(event: &TimerEvent) {
     // Send a message to a websocket
     send_message(&format!(
-        "{:?} {} {}",
-        &timer_state.action, &timer_state.phase, &timer_state.split_name
+        "{} {} {}",
+        event.action, event.phase, event.split_name
     ));
 }
 
+pub fn hotkeys_locked() -> bool {
+    LOCKED.load(Ordering::Relaxed)
+}
+
+/// The lock state as sent to the notifier's clients.
+pub fn lock_state() -> serde_json::Value {
+    serde_json::json!({ "locked": hotkeys_locked() })
+}
+
 pub fn send_message(s: &str) {
     NOTIFIER.write().unwrap().send(s);
 }
@@ -86,9 +251,11 @@ pub fn close_notifier() {
 impl WTimer {
     pub fn load_state(&mut self) {
         let path = config().state_file();
+        paths::adopt(&config().legacy_state_file(), &path);
         if let Some(path_str) = path.to_str() {
             if let Some(timer_state) = livesplit_core::TimerState::from_file(path_str) {
                 self.write().replace_state(&timer_state);
+                events::track(&self.read());
             }
         }
     }
@@ -105,6 +272,9 @@ impl WTimer {
     pub fn pause(&mut self) {
         self.write().toggle_pause();
     }
+    pub fn undo_all_pauses(&mut self) {
+        self.write().undo_all_pauses();
+    }
     pub fn split_or_start(&mut self) {
         self.write().split_or_start();
     }
@@ -131,80 +301,576 @@ impl WTimer {
         self.timer.read().unwrap()
     }
     fn new_from_splits(splits_file: PathBuf) -> Self {
-        let mut splits_file = splits_file;
         config_mut().set_splits_path(&splits_file);
-        splits_file.set_extension("lsz");
-        config_mut().set_state_file(&splits_file);
         Self::new()
     }
     fn new() -> Self {
-        let markers = config().build_marker_client();
         let layout = config().parse_layout_or_default();
         let run = config().parse_run_or_default();
         let timer = Timer::new(run).unwrap().into_shared();
         let auto_splitter = auto_splitting::Runtime::new(timer.clone());
-        let keys = Self::hotkey_setup(&timer);
         config().maybe_load_auto_splitter(&auto_splitter);
         config().configure_timer(&mut timer.write().unwrap());
-        timer.write().unwrap().set_on_timer_change(save_state);
-        Self {
+        events::track(&timer.read().unwrap());
+        timer.write().unwrap().set_on_timer_change(events::publish);
+        let markers = config()
+            .build_marker_client()
+            .map(stream_markers::Client::subscribe);
+        let mut wtimer = Self {
             timer,
-            markers,
+            _markers: markers,
             layout,
-            keys,
+            keys: HashMap::new(),
+            mouse: HashMap::new(),
+            chords: HashMap::new(),
+            pending: Vec::new(),
+            pending_since: Instant::now(),
+            activations: HashMap::new(),
+            activator: Activator::new(),
+            last_run: HashMap::new(),
+            held: HashMap::new(),
+            binder: None,
+            listed: Vec::new(),
+            help: None,
+            quit: false,
+            _hotkey_system: None,
+            _app_hotkeys: None,
+        };
+        wtimer.hotkey_setup();
+        wtimer
+    }
+    /// Applies the edits made to the prefs file on disk.
+    fn reload_config(&mut self) {
+        config().drop_stale_overrides();
+        let profile = config().selected_profile();
+        match Config::parse(&profile) {
+            Some(new) => self.apply_config(new),
+            None => log::warn!("Couldn't parse the preferences, keeping the current ones"),
+        }
+    }
+    fn next_profile(&mut self) {
+        config().drop_stale_overrides();
+        let profile = config().next_profile();
+        match Config::parse(&profile) {
+            Some(new) => {
+                self.apply_config(new);
+                let active = config().active_profile().map(str::to_owned);
+                send_message(&format!("Profile: {}", active.as_deref().unwrap_or("none")));
+            }
+            None => log::warn!("Couldn't switch to the next profile"),
+        }
+    }
+    fn apply_config(&mut self, new: Config) {
+        let changes = config().changes(&new);
+        *config_mut() = new;
+        if changes.splits {
+            let file = config().splits_path();
+            self.release_global_hotkeys();
+            *self = match file {
+                Some(file) => WTimer::new_from_splits(file),
+                None => WTimer::new(),
+            };
+            return;
+        }
+        if changes.layout {
+            self.layout = config().parse_layout_or_default();
+        }
+        if changes.hotkeys {
+            self.hotkey_setup();
+        }
+        if changes.timer {
+            config().configure_timer(&mut self.write());
+        }
+        if changes.connections {
+            let markers = config().build_marker_client();
+            self._markers = markers.map(stream_markers::Client::subscribe);
         }
     }
-    fn hotkey_setup(timer: &SharedTimer) -> HashMap<Key, Action> {
+    /// Binds the keys, registering the timer hotkeys and the keys listed in
+    /// `general.global-keys` as global hotkeys if enabled. Keys that can't be
+    /// registered globally only work in the window, as do scancodes and the
+    /// keys that are held or confirmed, since the global hotkeys only see the
+    /// keys being pressed. While locked, only the key to unlock
+    /// is bound.
+    fn hotkey_setup(&mut self) {
+        // The old global hotkeys have to be unregistered before registering
+        // them again.
+        self.release_global_hotkeys();
+        self.keys.clear();
+        self.mouse.clear();
+        self.chords.clear();
+        self.pending.clear();
+        self.activations.clear();
+        self.activator.cancel();
+        self.binder = None;
+        self.help = None;
+        self.listed.clear();
         let use_global_hotkeys = config().use_global_hotkeys();
-        let mut keys: HashMap<Key, Action> = HashMap::new();
-
-        // Default Value for key commands
-        let list = [
-            ("open", "Ctrl+O", Action::OpenSplits),
-            ("save", "Ctrl+S", Action::SaveSplits),
-            ("quit", "Ctrl+Q", Action::Quit),
-            ("hide", "Ctrl+T", Action::HideComparison),
-            ("layout", "Ctrl+L", Action::OpenLayout),
-            ("layout_up", "ArrowUp", Action::LayoutUp),
-            ("layout_down", "ArrowDown", Action::LayoutDown),
-        ];
-        for (name, dcmd, action) in list {
-            let key = config()
-                .keys()
-                .get(name)
-                .unwrap_or(&dcmd.parse().unwrap())
-                .into();
-            keys.insert(key, action);
+        let locked = hotkeys_locked();
+
+        let hook = if use_global_hotkeys {
+            Hook::new()
+                .map_err(|e| log::error!("Couldn't set up the global hotkeys: {}", e))
+                .ok()
+        } else {
+            None
+        };
+        for (name, _) in defaults::keys() {
+            let key = config().key(&name);
+            if let (Some(action), Some(key)) = (Action::from_name(&name), key) {
+                if locked && action != Action::ToggleLock {
+                    continue;
+                }
+                let activation = self.add_activation(&name, action);
+                let hook = hook.as_ref().filter(|_| config().is_global_key(&name));
+                let global = register_global(hook, &name, key, activation, action);
+                if global || self.bind_window_key(&name, key, action) {
+                    self.list(&name, key.to_string(), global, activation);
+                }
+            }
+        }
+        self._app_hotkeys = hook;
+        self.mouse_setup();
+
+        if locked {
+            self.sort_listed();
+            return;
         }
         if use_global_hotkeys {
-            let _hotkey_system = config().create_hotkey_system(timer.clone());
-        } else {
-            keys.insert(config().split_key().into(), Action::Split);
-            keys.insert(config().reset_key().into(), Action::Reset);
-            keys.insert(config().undo_key().into(), Action::Undo);
-            keys.insert(config().skip_key().into(), Action::Skip);
-            keys.insert(config().pause_key().into(), Action::Pause);
-            keys.insert(config().undo_all_key().into(), Action::UndoAllPauses);
-            keys.insert(config().prev_key().into(), Action::PreviousComparison);
-            keys.insert(config().next_key().into(), Action::NextComparison);
-            keys.insert(
-                config().toggle_timing_method_key().into(),
-                Action::ToggleTimingMethod,
+            self._hotkey_system = config().create_hotkey_system(self.timer.clone());
+            if self._hotkey_system.is_none() {
+                log::error!("Couldn't register the timer hotkeys globally");
+            }
+        }
+        self.chord_setup();
+        for (name, _) in defaults::HOTKEYS {
+            let key = config().hotkey(name);
+            if let (Some(action), Some(key)) = (Action::from_name(name), key) {
+                let activation = self.add_activation(name, action);
+                let in_system = config().global_hotkey(name).is_some();
+                if self._hotkey_system.is_some() && in_system {
+                    self.list(name, key.to_string(), true, activation);
+                    continue;
+                }
+                // Left out of the hotkey system, for its cooldown for example,
+                // which the app keeps track of.
+                let hook = self._app_hotkeys.as_ref();
+                let global = register_global(hook, name, key, activation, action);
+                if global || self.bind_window_key(name, key, action) {
+                    self.list(name, key.to_string(), global, activation);
+                }
+            }
+        }
+        self.sort_listed();
+    }
+    /// Adds a binding to the ones listed by the help.
+    fn list(&mut self, name: &str, input: String, global: bool, activation: Activation) {
+        self.listed.push(help::Entry {
+            name: name.to_owned(),
+            input,
+            global,
+            activation,
+        });
+    }
+    /// Lists the bindings in the order of the settings, so the ways to do the
+    /// same thing are next to each other.
+    fn sort_listed(&mut self) {
+        let names = bind::names();
+        let position = |name: &str| names.iter().position(|n| n == name);
+        self.listed.sort_by_key(|entry| position(&entry.name));
+    }
+    /// Remembers how the action of the key `name` is triggered.
+    fn add_activation(&mut self, name: &str, action: Action) -> Activation {
+        let activation = config().activation(name);
+        if !activation.mode.works_globally() && config().is_global(name) {
+            log::info!(
+                "`{}` only works in the window, it needs {:?}",
+                name,
+                activation.mode
             );
         }
-        keys
+        if !activation.is_plain() {
+            self.activations.insert(action, activation);
+        }
+        activation
+    }
+    /// Runs the action bound to `key` or starts activating it.
+    fn trigger(&mut self, input: activation::Input, action: Option<Action>) {
+        let binding = action.map(|action| {
+            let activation = self.activations.get(&action).copied();
+            (action, activation.unwrap_or(Activation::PRESS))
+        });
+        if let Some(action) = self.activator.press(input, binding) {
+            self.action(&action);
+        }
+    }
+    /// Runs the action whose key has been held long enough.
+    fn poll_activation(&mut self) {
+        if let Some(action) = self.activator.poll() {
+            self.action(&action);
+        }
+    }
+    fn chord_setup(&mut self) {
+        let chords = config().chords().clone();
+        for (sequence, name) in &chords {
+            let action = match Action::from_name(name) {
+                Some(action) => action,
+                None => {
+                    log::error!("Chord `{}`: there is no `{}`", sequence, name);
+                    continue;
+                }
+            };
+            match keys::parse_sequence(sequence) {
+                Ok(chord) => {
+                    self.chords.insert(chord, action);
+                    let activation = config().activation(name);
+                    self.list(name, sequence.clone(), false, activation);
+                }
+                Err(e) => log::error!("Chord `{}`: {}", sequence, e),
+            }
+        }
+    }
+    /// Binds the key in the window, returning whether it could be.
+    fn bind_window_key(&mut self, name: &str, binding: Binding, action: Action) -> bool {
+        match binding.key() {
+            Ok(key) => {
+                self.keys.insert(key, action);
+                true
+            }
+            Err(e) => {
+                log::error!("Can't use `{}` in the window: {}", name, e);
+                false
+            }
+        }
+    }
+    fn release_global_hotkeys(&mut self) {
+        self._hotkey_system = None;
+        self._app_hotkeys = None;
+    }
+    /// Handles a key going down or up in the window. Keys held down repeat,
+    /// only the first press counts.
+    fn key_input(&mut self, input: &KeyboardInput, modifiers: ModifiersState) {
+        match input.state {
+            ElementState::Pressed => {
+                if let Some(logged) = self.held.get_mut(&input.scancode) {
+                    if !*logged {
+                        log::warn!(
+                            "Ignoring the auto-repeat of {:?} until it is released",
+                            input.virtual_keycode
+                        );
+                        *logged = true;
+                    }
+                    return;
+                }
+                self.held.insert(input.scancode, false);
+                log::debug!(
+                    "Pressed {:?}, Scan{} as a scancode",
+                    input.virtual_keycode,
+                    input.scancode
+                );
+                if self.binder.is_some() {
+                    self.bind_key(input, modifiers);
+                    return;
+                }
+                // Bindings to the scancode win over the key it types.
+                let scan = Key::scan(input.scancode, modifiers);
+                let key = match input.virtual_keycode {
+                    Some(key) if !self.is_bound(&scan) => Key::from(key, modifiers),
+                    _ => scan,
+                };
+                self.handle_keypress(key);
+            }
+            ElementState::Released => {
+                self.held.remove(&input.scancode);
+                let scan = Code::Scan(input.scancode);
+                self.activator.release(activation::Input::Key(scan));
+                if let Some(key) = input.virtual_keycode {
+                    let key = Code::Virtual(key);
+                    self.activator.release(activation::Input::Key(key));
+                }
+            }
+        }
+    }
+    /// Whether the key is bound on its own or in a chord.
+    fn is_bound(&self, key: &Key) -> bool {
+        self.keys.contains_key(key) || self.chords.keys().any(|chord| chord.contains(key))
+    }
+    /// Binds the mouse buttons and wheel directions. They only work in the
+    /// window, and the wheel can't be held.
+    fn mouse_setup(&mut self) {
+        let mouse = config().mouse();
+        for (input, name) in mouse {
+            let mouse = match input.parse::<Mouse>() {
+                Ok(mouse) => mouse,
+                Err(e) => {
+                    log::error!("Mouse `{}`: {}", input, e);
+                    continue;
+                }
+            };
+            let action = match Action::from_name(&name) {
+                Some(action) => action,
+                None => {
+                    log::error!("Mouse `{}`: there is no `{}`", input, name);
+                    continue;
+                }
+            };
+            if hotkeys_locked() && action != Action::ToggleLock {
+                continue;
+            }
+            let activation = config().activation(&name);
+            if activation.mode == Mode::Hold && matches!(mouse.input, mouse::Input::Wheel(_)) {
+                log::error!("Mouse `{}`: the wheel can't be held for `{}`", input, name);
+                continue;
+            }
+            if !activation.is_plain() {
+                self.activations.insert(action, activation);
+            }
+            self.mouse.insert(mouse, action);
+            self.list(&name, input, false, activation);
+        }
+    }
+    fn mouse_input(&mut self, state: ElementState, button: MouseButton, modifiers: ModifiersState) {
+        let input = mouse::Input::Button(button);
+        match state {
+            ElementState::Pressed => {
+                let mouse = Mouse::new(input, modifiers);
+                log::debug!("Pressed {}", mouse);
+                self.click(mouse);
+            }
+            ElementState::Released => self.activator.release(activation::Input::Mouse(input)),
+        }
+    }
+    fn mouse_wheel(&mut self, delta: &MouseScrollDelta, modifiers: ModifiersState) {
+        if let Some(wheel) = Wheel::from_delta(delta) {
+            self.click(Mouse::new(mouse::Input::Wheel(wheel), modifiers));
+        }
+    }
+    fn click(&mut self, mouse: Mouse) {
+        if self.binder.is_some() {
+            return;
+        }
+        let action = self.mouse.get(&mouse).copied();
+        self.trigger(activation::Input::Mouse(mouse.input), action);
+    }
+    /// Forgets the keys that are down, their release goes to another window.
+    fn focus_lost(&mut self) {
+        self.held.clear();
+        self.activator.cancel();
+    }
+    /// Whether the action's cooldown is over, remembering that it runs if so.
+    fn cooled_down(&mut self, action: Action) -> bool {
+        let cooldown = self.activations.get(&action).and_then(Activation::cooldown);
+        let now = Instant::now();
+        if let (Some(cooldown), Some(last)) = (cooldown, self.last_run.get(&action)) {
+            let since = now - *last;
+            if since < cooldown {
+                log::warn!(
+                    "Ignored {:?}, {} ms after the last one",
+                    action,
+                    since.as_millis()
+                );
+                return false;
+            }
+        }
+        self.last_run.insert(action, now);
+        true
     }
     fn handle_keypress(&mut self, key: Key) {
-        let action = if let Some(action) = self.keys.get(&key) {
-            *action
-        } else {
+        self.expire_chord();
+        // The key completes or cancels the action being activated.
+        if self.activator.pending().is_some() && !key.is_modifier() {
+            let action = self.keys.get(&key).copied();
+            self.trigger(activation::Input::Key(key.key), action);
             return;
+        }
+        if !self.pending.is_empty() {
+            // Pressing a modifier on its own doesn't end a chord.
+            if key.is_modifier() {
+                return;
+            }
+            let mut chord = std::mem::take(&mut self.pending);
+            chord.push(key);
+            self.chord_key(chord);
+            return;
+        }
+        if self
+            .chords
+            .keys()
+            .any(|chord| chord.len() > 1 && chord[0] == key)
+        {
+            self.chord_key(vec![key]);
+            return;
+        }
+        let action = self.keys.get(&key).copied();
+        self.trigger(activation::Input::Key(key.key), action);
+    }
+    /// Handles the keys of a chord typed so far, waiting for more if they
+    /// start a longer one.
+    fn chord_key(&mut self, chord: Vec<Key>) {
+        if let Some(action) = self.chords.get(&chord) {
+            let action = *action;
+            let key = chord[chord.len() - 1];
+            self.trigger(activation::Input::Key(key.key), Some(action));
+        } else if self
+            .chords
+            .keys()
+            .any(|c| c.len() > chord.len() && c.starts_with(&chord))
+        {
+            self.pending = chord;
+            self.pending_since = Instant::now();
+        } else {
+            log::info!("No chord {}", chord_name(&chord));
+        }
+    }
+    /// Forgets the chord being typed once its time is up.
+    fn expire_chord(&mut self) {
+        if !self.pending.is_empty() && self.pending_since.elapsed() >= config().chord_timeout() {
+            log::info!("Chord {} timed out", chord_name(&self.pending));
+            self.pending.clear();
+        }
+    }
+    /// The part of the time to type the next key of a chord that is left.
+    fn chord_time_left(&self) -> Option<f32> {
+        if self.pending.is_empty() {
+            return None;
+        }
+        let timeout = config().chord_timeout().as_secs_f32();
+        Some(1.0 - self.pending_since.elapsed().as_secs_f32() / timeout)
+    }
+    /// Starts binding keys. The global hotkeys are released meanwhile, so
+    /// pressing the keys to bind doesn't trigger them.
+    fn start_binding(&mut self) {
+        self.release_global_hotkeys();
+        self.activator.cancel();
+        self.pending.clear();
+        self.binder = Some(Binder::new());
+    }
+    fn bind_key(&mut self, input: &KeyboardInput, modifiers: ModifiersState) {
+        let mut binder = match self.binder.take() {
+            Some(binder) => binder,
+            None => return,
         };
-        self.action(&action);
+        let scan = Key::scan(input.scancode, modifiers);
+        let key = input
+            .virtual_keycode
+            .map_or(scan, |key| Key::from(key, modifiers));
+        let bound_to = |key: &Key| self.bound_to(key).or_else(|| self.bound_to(&scan));
+        match binder.press(key, input.scancode, bound_to) {
+            Outcome::Continue => self.binder = Some(binder),
+            Outcome::Done => self.hotkey_setup(),
+            Outcome::Bind(name, binding) => {
+                config_mut().set_binding(&name, binding);
+                let message = if config_mut().save_config() {
+                    format!("Bound `{}` to {}", name, binding)
+                } else {
+                    format!(
+                        "Bound `{}` to {} but couldn't save it, fix {} first",
+                        name,
+                        binding,
+                        Config::config_file().display()
+                    )
+                };
+                log::info!("{}", message);
+                send_message(&message);
+                self.hotkey_setup();
+            }
+        }
+    }
+    /// The setting the key is bound to, in the window, globally or as the
+    /// start of a chord, by its path for chords.
+    fn bound_to(&self, key: &Key) -> Option<String> {
+        let names = bind::names();
+        if let Some(action) = self.keys.get(key) {
+            let name = names
+                .iter()
+                .find(|name| Action::from_name(name) == Some(*action));
+            return name.cloned();
+        }
+        let config = config();
+        let name = names.into_iter().find(|name| {
+            let binding = config.binding(name);
+            binding.and_then(|binding| binding.key().ok()) == Some(*key)
+        });
+        name.or_else(|| {
+            let (chord, _) = self.chords.iter().find(|(chord, _)| chord[0] == *key)?;
+            Some(format!("chords.bindings.{}", chord_name(chord)))
+        })
+    }
+    /// Shows the help, or its next page, or hides it after the last one.
+    fn show_help(&mut self) {
+        match &mut self.help {
+            None => self.help = Some(Help::new(&self.listed)),
+            Some(help) => {
+                if !help.next_page(&self.listed) {
+                    self.help = None;
+                }
+            }
+        }
+    }
+    fn draw_help(&mut self, canvas: &mut Canvas<'_>) {
+        if let Some(help) = &mut self.help {
+            help.draw(&self.timer.read().unwrap(), canvas);
+        }
+    }
+    fn toggle_lock(&mut self) {
+        let locked = !hotkeys_locked();
+        LOCKED.store(locked, Ordering::Relaxed);
+        log::info!("{} the hotkeys", if locked { "Locked" } else { "Unlocked" });
+        NOTIFIER.write().unwrap().send(lock_state());
+        self.hotkey_setup();
+    }
+    fn title(&self) -> String {
+        if hotkeys_locked() {
+            let key = config().key("lock");
+            let key = key.map_or_else(|| "the lock key".to_owned(), |key| key.to_string());
+            format!("LiveSplit One - Locked, {} unlocks", key)
+        } else if let Some(help) = &self.help {
+            format!("LiveSplit One - {}", help.title())
+        } else if let Some(binder) = &self.binder {
+            let current = config().binding(binder.selected());
+            format!("LiveSplit One - {}", binder.prompt(current))
+        } else if let Some(pending) = self.activator.pending() {
+            let action = format!("{:?}", pending.action);
+            match pending.activation.mode {
+                Mode::Hold => format!("LiveSplit One - Hold to {}", action),
+                Mode::DoubleTap => format!("LiveSplit One - Press again to {}", action),
+                _ => format!(
+                    "LiveSplit One - Press Enter to {}, Escape to cancel",
+                    action
+                ),
+            }
+        } else if self.pending.is_empty() {
+            "LiveSplit One".to_owned()
+        } else {
+            format!("LiveSplit One - {} ...", chord_name(&self.pending))
+        }
+    }
+    fn draw_indicators(&self, canvas: &mut Canvas<'_>) {
+        if hotkeys_locked() {
+            canvas.frame(LOCK_COLOR);
+        }
+        if self.binder.is_some() {
+            canvas.top_bar(1.0, BIND_COLOR);
+        }
+        if let Some(left) = self.chord_time_left() {
+            canvas.top_bar(left, CHORD_COLOR);
+        }
+        if let (Some(pending), Some(progress)) =
+            (self.activator.pending(), self.activator.progress())
+        {
+            let color = match pending.activation.mode {
+                Mode::Hold => HOLD_COLOR,
+                _ => CONFIRM_COLOR,
+            };
+            canvas.bottom_bar(progress, color);
+        }
     }
     fn open_layout(&mut self) -> Result<(), ()> {
         let path = pick_layout_file().ok_or(())?;
-        let file = std::fs::read_to_string(&path).or(Err(()))?;
+        self.load_layout(&path)
+    }
+    fn load_layout(&mut self, path: &Path) -> Result<(), ()> {
+        let file = std::fs::read_to_string(path).or(Err(()))?;
         if let Ok(settings) = LayoutSettings::from_json(Cursor::new(&file)) {
             self.layout = Layout::from_settings(settings);
         } else if let Ok(layout) = layout::parser::parse(&file) {
@@ -213,15 +879,43 @@ impl WTimer {
             println!("Error parsing layout");
             return Ok(());
         }
-        config_mut().set_layout_path(&path);
-        config().save_config();
+        config_mut().set_layout_path(path);
+        config_mut().add_recent_layout(path);
+        config_mut().save_config();
 
         Ok(())
     }
+    fn open_splits(&mut self, path: PathBuf) {
+        if !path.is_file() {
+            log::warn!("{} doesn't exist anymore", path.display());
+            config_mut().remove_recent(&path);
+            config_mut().save_config();
+            return;
+        }
+        config_mut().add_recent_splits(&path);
+        self.release_global_hotkeys();
+        *self = WTimer::new_from_splits(path);
+        config_mut().save_config();
+    }
+    fn open_recent_layout(&mut self, path: PathBuf) {
+        if self.load_layout(&path).is_err() {
+            log::warn!("Couldn't open {}", path.display());
+            config_mut().remove_recent(&path);
+            config_mut().save_config();
+        }
+    }
     fn action(&mut self, action: &Action) {
+        if hotkeys_locked() && *action != Action::ToggleLock {
+            log::info!("Ignored {:?}, the hotkeys are locked", action);
+            return;
+        }
+        if !self.cooled_down(*action) {
+            return;
+        }
         match action {
             Action::Split => self.split_or_start(),
             Action::Pause => self.pause(),
+            Action::UndoAllPauses => self.undo_all_pauses(),
             Action::Reset => self.reset(),
             Action::Undo => self.undo_split(),
             Action::Skip => self.skip_split(),
@@ -233,27 +927,87 @@ impl WTimer {
             Action::SaveSplits => config().save_splits(&self.read()),
             Action::OpenSplits => {
                 if let Some(file) = pick_splits_file() {
-                    *self = WTimer::new_from_splits(file);
-                    config().save_config();
+                    self.open_splits(file);
+                }
+            }
+            Action::RecentSplits(n) => {
+                let file = config().recent_splits().get(*n).cloned();
+                if let Some(file) = file {
+                    self.open_splits(file);
+                }
+            }
+            Action::NextSplits => {
+                let file = config().recent_splits().iter().skip(1).last().cloned();
+                if let Some(file) = file {
+                    self.open_splits(file);
+                }
+            }
+            Action::RecentLayout(n) => {
+                let file = config().recent_layouts().get(*n).cloned();
+                if let Some(file) = file {
+                    self.open_recent_layout(file);
+                }
+            }
+            Action::NextLayout => {
+                let file = config().recent_layouts().iter().skip(1).last().cloned();
+                if let Some(file) = file {
+                    self.open_recent_layout(file);
                 }
             }
             Action::OpenLayout => self.open_layout().unwrap_or(()),
             Action::ToggleTimingMethod => self.toggle_timing_method(),
-            Action::Quit => {} //self.skip_split(),
-            _ => unimplemented!("{:?}", action),
+            Action::NextProfile => self.next_profile(),
+            Action::Bind => self.start_binding(),
+            Action::ToggleLock => self.toggle_lock(),
+            Action::Help => self.show_help(),
+            Action::Quit => self.quit = true,
+        }
+    }
+}
+/// Registers the key `name` as a global hotkey, returning whether it worked.
+/// Scancodes and keys that are held or confirmed can't be.
+fn register_global(
+    hook: Option<&Hook>,
+    name: &str,
+    binding: Binding,
+    activation: Activation,
+    action: Action,
+) -> bool {
+    let hook = match hook {
+        Some(hook) if activation.mode.works_globally() => hook,
+        _ => return false,
+    };
+    let hotkey = match binding.hotkey() {
+        Some(hotkey) => hotkey,
+        None => {
+            log::info!("`{}` is a scancode, it only works in the window", name);
+            return false;
+        }
+    };
+    match hook.register(hotkey, move || send_user_event(UserEvent::Action(action))) {
+        Ok(()) => true,
+        Err(e) => {
+            log::warn!("Couldn't register {} globally: {}", hotkey, e);
+            false
         }
     }
 }
 fn pick_splits_file() -> Option<PathBuf> {
+    let dir = config()
+        .splits_dir()
+        .map_or_else(|| ".".into(), Path::to_path_buf);
     FileDialog::new()
         .add_filter("splits", &["lss", "rs"])
-        .set_directory(".")
+        .set_directory(dir)
         .pick_file()
 }
 fn pick_layout_file() -> Option<PathBuf> {
+    let dir = config()
+        .layout_dir()
+        .map_or_else(|| ".".into(), Path::to_path_buf);
     FileDialog::new()
         .add_filter("layout", &["json", "ls1l"])
-        .set_directory(".")
+        .set_directory(dir)
         .pick_file()
 }
 
@@ -273,51 +1027,140 @@ struct Args {
     /// Splits file
     #[arg(short, long)]
     splits: Option<PathBuf>,
-    /// State file
+    /// Layout file
     #[arg(short, long)]
-    layout: Option<String>,
+    layout: Option<PathBuf>,
     /// State file
     #[arg(short, long)]
     timing_file: Option<PathBuf>,
+    /// Preferences file to use instead of the default one
+    #[arg(short, long, env = "LIVESPLIT_ONE_CONFIG")]
+    config: Option<PathBuf>,
+    /// Override a setting, e.g. `general.comparison="Best Segments"`
+    #[arg(long = "set", value_name = "SETTING=VALUE", value_parser = Override::parse_arg)]
+    overrides: Vec<Override>,
+    /// Check the preferences file for errors and exit
+    #[arg(long)]
+    check_config: bool,
+    /// Print every setting in effect, and where it comes from, and exit
+    #[arg(long)]
+    print_config: bool,
+    /// Profile from the preferences file to use
+    #[arg(short, long, env = "LIVESPLIT_ONE_PROFILE")]
+    profile: Option<String>,
 }
 
-fn scroll_up(delta: &MouseScrollDelta) -> bool {
-    match delta {
-        MouseScrollDelta::LineDelta(y, ..) => y < &0.0,
-        MouseScrollDelta::PixelDelta(winit::dpi::PhysicalPosition { y, .. }) => y < &0.0,
+impl Args {
+    /// The overrides from the environment followed by the ones given on the
+    /// command line, so the command line wins.
+    fn overrides(&self) -> Vec<Override> {
+        // Paths on the command line are relative to the working directory,
+        // not to wherever the setting would be relative to.
+        let path = |path: &PathBuf| {
+            let path = std::env::current_dir().map_or_else(|_| path.clone(), |dir| dir.join(path));
+            Value::String(path.display().to_string())
+        };
+        let mut overrides = Override::from_env();
+        if self.global_hotkeys {
+            overrides.push(Override::new(
+                Source::CommandLine,
+                "general.use-global-hotkeys",
+                Value::Bool(true),
+            ));
+        }
+        let paths = [
+            ("general.splits", &self.splits),
+            ("general.layout", &self.layout),
+            ("general.state-file", &self.timing_file),
+        ];
+        for (setting, value) in paths {
+            if let Some(value) = value {
+                overrides.push(Override::new(Source::CommandLine, setting, path(value)));
+            }
+        }
+        overrides.extend(self.overrides.iter().cloned());
+        overrides
     }
 }
 
-fn key_code(input: &KeyboardInput, pressed: bool) -> Option<VirtualKeyCode> {
-    if input.state == ElementState::Pressed && pressed {
-        input.virtual_keycode
-    } else {
-        None
+fn check_config(profile: &Profile) -> ! {
+    let path = Config::config_file();
+    match Config::check(&path, profile) {
+        Ok(check) => {
+            for error in &check.errors {
+                println!("error: {}", error);
+            }
+            for warning in &check.warnings {
+                println!("warning: {}", warning);
+            }
+            if check.errors.is_empty() {
+                println!("{} is valid", path.display());
+                std::process::exit(0);
+            }
+        }
+        Err(e) => println!("Couldn't read {}: {}", path.display(), e),
+    }
+    std::process::exit(1);
+}
+
+fn print_config(profile: &Profile) -> ! {
+    let config = match Config::check(Config::config_file(), profile) {
+        Ok(check) => match check.config {
+            Some(config) => config,
+            None => {
+                for error in &check.errors {
+                    println!("error: {}", error);
+                }
+                std::process::exit(1);
+            }
+        },
+        Err(_) => Config::default(),
+    };
+    for setting in config.resolved() {
+        println!("{}", setting);
     }
+    std::process::exit(0);
 }
 
 fn main() {
-    *config_mut() = Config::parse().unwrap_or_default();
-    config().setup_logging();
     let args = Args::parse();
-    if args.global_hotkeys {
-        config_mut().set_use_global_hotkeys(args.global_hotkeys);
+    if let Some(path) = &args.config {
+        Config::set_config_file(path.clone());
+    }
+    Config::set_overrides(args.overrides());
+    let profile = Profile::from(args.profile.as_deref());
+    if args.check_config {
+        check_config(&profile);
     }
-    if let Some(ref val) = args.splits {
-        config_mut().set_splits_path(&val.to_path_buf());
+    if args.print_config {
+        print_config(&profile);
     }
-    if let Some(val) = args.timing_file {
-        config_mut().set_state_file(&val);
+    let (prefs, check) = Config::load(&profile);
+    *config_mut() = prefs;
+    config().setup_logging();
+    if let Some(check) = check {
+        check.report();
     }
+    config().paths().create_all();
 
     let file = config().splits_path();
+    let _persistence = events::subscribe("persistence", state_saver());
+    let _notifier = events::subscribe("notifier", notify_timer_change);
+    let _hooks = events::subscribe("hooks", hooks::run);
+
+    // Created before the timer, so the global hotkeys can send it actions.
+    let event_loop = EventLoopBuilder::<UserEvent>::with_user_event().build();
+    let proxy = event_loop.create_proxy();
+    *PROXY.lock().unwrap() = Some(event_loop.create_proxy());
+
     let mut wtimer = if let Some(file) = file {
         WTimer::new_from_splits(file)
     } else {
         WTimer::new()
     };
-
-    let event_loop = EventLoop::new();
+    Config::watch(move || {
+        let _ = proxy.send_event(UserEvent::ConfigChanged);
+    });
     let size = config().window_size();
 
     let size = winit::dpi::LogicalSize::new(size[0] as f64, size[1] as f64);
@@ -337,6 +1180,7 @@ fn main() {
     let mut buf = Vec::new();
 
     let mut modifiers: ModifiersState = ModifiersState::empty();
+    let mut window_title = wtimer.title();
 
     send_message("init");
 
@@ -346,24 +1190,25 @@ fn main() {
             Event::WindowEvent { event: ref ev, .. } => match ev {
                 WindowEvent::CloseRequested { .. } => control_flow.set_exit(),
                 WindowEvent::Resized(_size) => {}
-                WindowEvent::MouseWheel { delta, .. } => {
-                    if scroll_up(delta) {
-                        wtimer.layout.scroll_up();
-                    } else {
-                        wtimer.layout.scroll_down();
-                    }
-                }
-                WindowEvent::KeyboardInput { input, .. } => {
-                    if let Some(key) = key_code(input, true) {
-                        let key = Key::from(key, modifiers);
-                        wtimer.handle_keypress(key)
-                    }
+                WindowEvent::MouseWheel { delta, .. } => wtimer.mouse_wheel(delta, modifiers),
+                WindowEvent::MouseInput { state, button, .. } => {
+                    wtimer.mouse_input(*state, *button, modifiers)
                 }
+                WindowEvent::KeyboardInput { input, .. } => wtimer.key_input(input, modifiers),
+                WindowEvent::Focused(false) => wtimer.focus_lost(),
                 WindowEvent::ModifiersChanged(mods) => {
                     modifiers = *mods;
                 }
                 _ => {}
             },
+            Event::UserEvent(UserEvent::ConfigChanged) => wtimer.reload_config(),
+            Event::UserEvent(UserEvent::Action(action)) => {
+                wtimer.trigger(activation::Input::Global, Some(action))
+            }
+            Event::LoopDestroyed => {
+                events::drain();
+                persist::flush();
+            }
             Event::RedrawRequested(_) => {
                 let (width, height) = {
                     let size = window.inner_size();
@@ -371,7 +1216,6 @@ fn main() {
                 };
                 {
                     let timer = wtimer.timer.read().unwrap();
-                    wtimer.markers.tick(&timer);
                     wtimer
                         .layout
                         .update_state(&mut layout_state, &timer.snapshot());
@@ -385,11 +1229,24 @@ fn main() {
                     bytemuck::cast_slice_mut(&mut buf),
                     bytemuck::cast_slice(renderer.image_data()),
                 );
+                wtimer.expire_chord();
+                wtimer.poll_activation();
+                let mut canvas = Canvas::new(&mut buf, width, height);
+                wtimer.draw_help(&mut canvas);
+                wtimer.draw_indicators(&mut canvas);
+                let title = wtimer.title();
+                if title != window_title {
+                    window.set_title(&title);
+                    window_title = title;
+                }
                 surface.set_buffer(&buf, width as u16, height as u16);
             }
             _ => {}
         }
         if let Event::RedrawRequested(_) = event {}
+        if wtimer.quit {
+            control_flow.set_exit();
+        }
         window.request_redraw();
     });
 }
@@ -0,0 +1,201 @@
+use serde_yaml::{Mapping, Value};
+use std::path::Path;
+
+/// The version of the prefs file written by this build. Bump it and add a
+/// step to `MIGRATIONS` whenever the meaning of a setting changes.
+pub const VERSION: u64 = 2;
+
+/// `MIGRATIONS[n]` upgrades a prefs file from version `n` to `n + 1`. Files
+/// without a version are version 0.
+const MIGRATIONS: [fn(&mut Value); VERSION as usize] = [rename_key_names, state_file_in_state_dir];
+
+/// The version the prefs file was written with.
+pub fn version(value: &Value) -> Result<u64, String> {
+    match value.get("version") {
+        None | Some(Value::Null) => Ok(0),
+        Some(version) => version
+            .as_u64()
+            .ok_or_else(|| format!("`version` must be a number, got {:?}", version)),
+    }
+}
+
+/// Upgrades the prefs file to the current version. Returns the version it
+/// had, or an error if it was written by a newer build.
+pub fn migrate(value: &mut Value) -> Result<u64, String> {
+    let from = version(value)?;
+    if from > VERSION {
+        return Err(format!(
+            "the preferences are version {}, but this build only understands up to version {}. \
+             Update LiveSplit One or point it at another file",
+            from, VERSION
+        ));
+    }
+    for step in &MIGRATIONS[from as usize..] {
+        step(value);
+        for profile in profiles_mut(value) {
+            step(profile);
+        }
+    }
+    if let Value::Mapping(mapping) = value {
+        mapping.insert("version".into(), VERSION.into());
+    }
+    Ok(from)
+}
+
+fn profiles_mut(value: &mut Value) -> Vec<&mut Value> {
+    match value.get_mut("profiles") {
+        Some(Value::Mapping(profiles)) => profiles.values_mut().collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Version 1: hotkeys use the W3C key names (`ArrowRight`, `Numpad7`) instead
+/// of the old ones (`Right`, `NumPad7`), and `next_comparision` is spelled
+/// correctly.
+fn rename_key_names(value: &mut Value) {
+    for section in ["hotkeys", "keys"] {
+        if let Some(Value::Mapping(bindings)) = value.get_mut(section) {
+            if let Some(binding) = bindings.remove("next_comparision") {
+                if !bindings.contains_key("next_comparison") {
+                    bindings.insert("next_comparison".into(), binding);
+                }
+            }
+            rename_bindings(bindings);
+        }
+    }
+}
+
+fn rename_bindings(bindings: &mut Mapping) {
+    for binding in bindings.values_mut() {
+        if let Value::String(keys) = binding {
+            let renamed: Vec<String> = keys.split('+').map(rename_key).collect();
+            *keys = renamed.join("+");
+        }
+    }
+}
+
+fn rename_key(key: &str) -> String {
+    let key = key.trim();
+    if let Some(rest) = key.strip_prefix("NumPad") {
+        return format!("Numpad{}", rest);
+    }
+    match key {
+        "Left" | "Right" | "Up" | "Down" => format!("Arrow{}", key),
+        _ => key.to_owned(),
+    }
+}
+
+/// Version 2: state files are kept in the state directory and `state-file` is
+/// relative to it. The state file that used to be put next to the splits file
+/// keeps its name, and the old default is the default anyway.
+fn state_file_in_state_dir(value: &mut Value) {
+    let general = match value.get_mut("general") {
+        Some(Value::Mapping(general)) => general,
+        _ => return,
+    };
+    let state_file = match general.get("state-file").and_then(Value::as_str) {
+        Some(state_file) => Path::new(state_file).to_path_buf(),
+        None => return,
+    };
+    let next_to_splits = general
+        .get("splits")
+        .and_then(Value::as_str)
+        .is_some_and(|splits| Path::new(splits).with_extension("lsz") == state_file);
+    if state_file == Path::new("livesplit_state.lsz") {
+        general.remove("state-file");
+    } else if next_to_splits {
+        if let Some(name) = state_file.file_name() {
+            let name = name.to_string_lossy().into_owned();
+            general.insert("state-file".into(), name.into());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn yaml(text: &str) -> Value {
+        serde_yaml::from_str(text).unwrap()
+    }
+
+    #[test]
+    fn files_without_a_version_are_version_0() {
+        assert_eq!(version(&yaml("general: {}")), Ok(0));
+        assert_eq!(version(&yaml("version: null")), Ok(0));
+        assert_eq!(version(&yaml("version: 1")), Ok(1));
+        assert!(version(&yaml("version: two")).is_err());
+    }
+
+    #[test]
+    fn renames_the_old_key_names() {
+        let mut value = yaml(
+            "hotkeys:\n  split: NumPad1\n  next_comparision: Right\n\
+             keys:\n  undo: Ctrl + Left\n",
+        );
+        rename_key_names(&mut value);
+        let expected = yaml(
+            "hotkeys:\n  split: Numpad1\n  next_comparison: ArrowRight\n\
+             keys:\n  undo: Ctrl+ArrowLeft\n",
+        );
+        assert_eq!(value, expected);
+    }
+
+    #[test]
+    fn keeps_the_new_spelling_over_the_old_one() {
+        let mut value = yaml("hotkeys:\n  next_comparision: Up\n  next_comparison: Down\n");
+        rename_key_names(&mut value);
+        assert_eq!(value, yaml("hotkeys:\n  next_comparison: ArrowDown\n"));
+    }
+
+    #[test]
+    fn moves_the_state_file_into_the_state_dir() {
+        let mut default = yaml("general:\n  state-file: livesplit_state.lsz\n");
+        state_file_in_state_dir(&mut default);
+        assert_eq!(default, yaml("general: {}"));
+
+        let mut next_to_splits =
+            yaml("general:\n  splits: /runs/any%.lss\n  state-file: /runs/any%.lsz\n");
+        state_file_in_state_dir(&mut next_to_splits);
+        let expected = yaml("general:\n  splits: /runs/any%.lss\n  state-file: any%.lsz\n");
+        assert_eq!(next_to_splits, expected);
+
+        let mut elsewhere = yaml("general:\n  state-file: /somewhere/else.lsz\n");
+        state_file_in_state_dir(&mut elsewhere);
+        assert_eq!(
+            elsewhere,
+            yaml("general:\n  state-file: /somewhere/else.lsz\n")
+        );
+    }
+
+    #[test]
+    fn upgrades_the_file_and_its_profiles() {
+        let mut value = yaml(
+            "hotkeys:\n  split: Left\n\
+             profiles:\n  race:\n    hotkeys:\n      split: NumPad2\n",
+        );
+        assert_eq!(migrate(&mut value), Ok(0));
+        let expected = yaml(&format!(
+            "hotkeys:\n  split: ArrowLeft\n\
+             profiles:\n  race:\n    hotkeys:\n      split: Numpad2\n\
+             version: {}\n",
+            VERSION
+        ));
+        assert_eq!(value, expected);
+    }
+
+    #[test]
+    fn files_from_newer_builds_are_refused() {
+        let mut value = yaml(&format!(
+            "version: {}\nhotkeys:\n  split: Left\n",
+            VERSION + 1
+        ));
+        let error = migrate(&mut value).unwrap_err();
+        assert!(
+            error.contains("only understands up to version"),
+            "{}",
+            error
+        );
+        assert_eq!(value["hotkeys"]["split"], "Left");
+    }
+}
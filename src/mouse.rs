@@ -0,0 +1,104 @@
+use crate::keys::{self, ParseKeyError};
+use std::{fmt, str::FromStr};
+use winit::dpi::PhysicalPosition;
+use winit::event::{ModifiersState, MouseButton, MouseScrollDelta};
+
+/// The numbers winit gives the side buttons, back first.
+#[cfg(windows)]
+const SIDE_BUTTONS: [u16; 2] = [1, 2];
+#[cfg(target_os = "macos")]
+const SIDE_BUTTONS: [u16; 2] = [3, 4];
+#[cfg(not(any(windows, target_os = "macos")))]
+const SIDE_BUTTONS: [u16; 2] = [8, 9];
+
+#[derive(Debug, Hash, PartialEq, Eq, Copy, Clone)]
+pub enum Wheel {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Wheel {
+    /// The way the wheel was turned, going by the axis it was turned most
+    /// along.
+    pub fn from_delta(delta: &MouseScrollDelta) -> Option<Self> {
+        let (x, y) = match *delta {
+            MouseScrollDelta::LineDelta(x, y) => (x as f64, y as f64),
+            MouseScrollDelta::PixelDelta(PhysicalPosition { x, y }) => (x, y),
+        };
+        if x == 0.0 && y == 0.0 {
+            None
+        } else if y.abs() >= x.abs() {
+            Some(if y > 0.0 { Wheel::Up } else { Wheel::Down })
+        } else {
+            Some(if x > 0.0 { Wheel::Right } else { Wheel::Left })
+        }
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Copy, Clone)]
+pub enum Input {
+    Button(MouseButton),
+    Wheel(Wheel),
+}
+
+/// A mouse button or wheel direction with the modifiers held, written like
+/// `Ctrl+Middle`, `Back` or `Shift+WheelUp`. Buttons without a name are
+/// written `Button<n>`.
+#[derive(Debug, Hash, PartialEq, Eq, Copy, Clone)]
+pub struct Mouse {
+    pub input: Input,
+    pub mods: ModifiersState,
+}
+
+impl Mouse {
+    pub fn new(input: Input, mods: ModifiersState) -> Self {
+        Self { input, mods }
+    }
+}
+
+fn str_to_input(s: &str) -> Result<Input, ParseKeyError> {
+    let button = match s {
+        "Left" => MouseButton::Left,
+        "Right" => MouseButton::Right,
+        "Middle" => MouseButton::Middle,
+        "Back" => MouseButton::Other(SIDE_BUTTONS[0]),
+        "Forward" => MouseButton::Other(SIDE_BUTTONS[1]),
+        "WheelUp" => return Ok(Input::Wheel(Wheel::Up)),
+        "WheelDown" => return Ok(Input::Wheel(Wheel::Down)),
+        "WheelLeft" => return Ok(Input::Wheel(Wheel::Left)),
+        "WheelRight" => return Ok(Input::Wheel(Wheel::Right)),
+        _ => {
+            let n = s.strip_prefix("Button").and_then(|n| n.parse().ok());
+            MouseButton::Other(n.ok_or_else(|| ParseKeyError::new(s))?)
+        }
+    };
+    Ok(Input::Button(button))
+}
+
+impl FromStr for Mouse {
+    type Err = ParseKeyError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (mods, input) = match s.rsplit_once('+') {
+            Some((mods, input)) => (keys::str_to_mods(mods.trim())?, input.trim()),
+            None => (ModifiersState::empty(), s),
+        };
+        Ok(Self::new(str_to_input(input)?, mods))
+    }
+}
+
+impl fmt::Display for Mouse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        keys::write_mods(f, self.mods)?;
+        match self.input {
+            Input::Button(MouseButton::Left) => f.write_str("Left"),
+            Input::Button(MouseButton::Right) => f.write_str("Right"),
+            Input::Button(MouseButton::Middle) => f.write_str("Middle"),
+            Input::Button(MouseButton::Other(n)) if n == SIDE_BUTTONS[0] => f.write_str("Back"),
+            Input::Button(MouseButton::Other(n)) if n == SIDE_BUTTONS[1] => f.write_str("Forward"),
+            Input::Button(MouseButton::Other(n)) => write!(f, "Button{}", n),
+            Input::Wheel(wheel) => write!(f, "Wheel{:?}", wheel),
+        }
+    }
+}
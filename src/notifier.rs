@@ -23,8 +23,20 @@ async fn ws_event(event_hub: &EventHub, clients: &mut HashMap<u64, Responder>) {
             );
             // retrieve this client's `Responder`:
             let responder = clients.get(&client_id).unwrap();
-            // echo the message back:
-            responder.send(message);
+            match &message {
+                // the settings in effect, with where each comes from
+                Message::Text(text) if text.trim() == "config" => {
+                    responder.send(Message::Text(resolved_config()));
+                }
+                // whether the hotkeys are locked, also sent when it changes
+                Message::Text(text) if text.trim() == "lock" => {
+                    responder.send(Message::Text(crate::lock_state().to_string()));
+                }
+                // echo the message back:
+                _ => {
+                    responder.send(message);
+                }
+            }
         }
     }
 }
@@ -41,6 +53,11 @@ async fn local_event(rx: &mut mpsc::UnboundedReceiver<Msg>) -> Msg {
         }
     }
 }
+fn resolved_config() -> String {
+    let settings = crate::config().resolved();
+    serde_json::json!({ "config": settings }).to_string()
+}
+
 #[derive(Debug, PartialEq, Clone)]
 enum Msg {
     Value(serde_json::Value),
@@ -0,0 +1,66 @@
+use std::ops::Range;
+
+/// Indicators drawn over the rendered layout. The buffer holds one `0xAARRGGBB`
+/// pixel per `u32`, row by row, as handed to the surface.
+pub struct Canvas<'a> {
+    buf: &'a mut [u32],
+    width: usize,
+    height: usize,
+}
+
+impl<'a> Canvas<'a> {
+    pub fn new(buf: &'a mut [u32], width: usize, height: usize) -> Self {
+        Self { buf, width, height }
+    }
+
+    pub fn size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    /// Draws an image the size of the canvas over it. The image is RGBA with
+    /// premultiplied alpha, as rendered.
+    pub fn blend(&mut self, image: &[u8]) {
+        for (dst, src) in self.buf.iter_mut().zip(image.chunks_exact(4)) {
+            let old = *dst;
+            let inverse = 255 - src[3] as u32;
+            let channel = |shift: u32, src: u8| {
+                let old = (old >> shift) & 0xFF;
+                (src as u32 + old * inverse / 255).min(0xFF) << shift
+            };
+            *dst =
+                channel(24, src[3]) | channel(16, src[0]) | channel(8, src[1]) | channel(0, src[2]);
+        }
+    }
+
+    pub fn fill(&mut self, x: Range<usize>, y: Range<usize>, color: u32) {
+        let x = x.start.min(self.width)..x.end.min(self.width);
+        for row in y.start.min(self.height)..y.end.min(self.height) {
+            self.buf[row * self.width..][x.clone()].fill(color);
+        }
+    }
+
+    /// A bar along the top edge, filled from the left to `fraction` of the
+    /// width.
+    pub fn top_bar(&mut self, fraction: f32, color: u32) {
+        let end = (self.width as f32 * fraction.clamp(0.0, 1.0)) as usize;
+        self.fill(0..end, 0..BAR_HEIGHT, color);
+    }
+
+    /// A frame along all edges.
+    pub fn frame(&mut self, color: u32) {
+        let (width, height) = (self.width, self.height);
+        self.fill(0..width, 0..BAR_HEIGHT, color);
+        self.fill(0..width, height.saturating_sub(BAR_HEIGHT)..height, color);
+        self.fill(0..BAR_HEIGHT, 0..height, color);
+        self.fill(width.saturating_sub(BAR_HEIGHT)..width, 0..height, color);
+    }
+
+    /// Like `top_bar`, along the bottom edge.
+    pub fn bottom_bar(&mut self, fraction: f32, color: u32) {
+        let end = (self.width as f32 * fraction.clamp(0.0, 1.0)) as usize;
+        let top = self.height.saturating_sub(BAR_HEIGHT);
+        self.fill(0..end, top..self.height, color);
+    }
+}
+
+const BAR_HEIGHT: usize = 4;
@@ -0,0 +1,219 @@
+use serde_yaml::{Mapping, Value};
+use std::{env, fmt};
+
+/// Environment variables starting with this override settings, with `__`
+/// separating the parts of the setting's path. For example
+/// `LIVESPLIT_ONE_GENERAL__COMPARISON` overrides `general.comparison`.
+pub const ENV_PREFIX: &str = "LIVESPLIT_ONE_";
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Source {
+    Environment,
+    CommandLine,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Source::Environment => "environment",
+            Source::CommandLine => "command line",
+        })
+    }
+}
+
+/// A setting that is layered over the prefs file without being saved to it.
+#[derive(Debug, Clone)]
+pub struct Override {
+    pub source: Source,
+    path: Vec<String>,
+    value: Value,
+}
+
+impl fmt::Display for Override {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} override `{}`", self.source, self.path.join("."))
+    }
+}
+
+impl Override {
+    pub fn new(source: Source, path: &str, value: Value) -> Self {
+        Self {
+            source,
+            path: path.split('.').map(str::to_owned).collect(),
+            value,
+        }
+    }
+
+    /// Parses a `path.to.setting=value` argument. The value is read as YAML,
+    /// so `true` and `300` aren't strings.
+    pub fn parse_arg(s: &str) -> Result<Self, String> {
+        let (path, value) = s
+            .split_once('=')
+            .ok_or_else(|| format!("expected `setting=value`, got `{}`", s))?;
+        let path = path.trim();
+        if path.is_empty() || path.split('.').any(str::is_empty) {
+            return Err(format!("`{}` is not a valid setting", path));
+        }
+        Ok(Self::new(Source::CommandLine, path, parse_value(value)))
+    }
+
+    /// The overrides set through environment variables, in a stable order.
+    /// `LIVESPLIT_ONE_CONFIG` and `LIVESPLIT_ONE_PROFILE` pick the prefs file
+    /// and profile instead and are skipped.
+    pub fn from_env() -> Vec<Self> {
+        let mut vars: Vec<(String, String)> = env::vars()
+            .filter_map(|(key, value)| {
+                let name = key.strip_prefix(ENV_PREFIX)?;
+                if name == "CONFIG" || name == "PROFILE" || name.is_empty() {
+                    return None;
+                }
+                Some((name.to_lowercase().replace("__", "."), value))
+            })
+            .collect();
+        vars.sort();
+        vars.into_iter()
+            .map(|(path, value)| Self::new(Source::Environment, &path, parse_value(&value)))
+            .collect()
+    }
+
+    /// Sets the value in `value`. Keys are matched against the existing ones
+    /// and the ones in `schema`, ignoring case and whether `-` or `_` is used.
+    pub fn apply(&self, value: &mut Value, schema: &Value) {
+        let mut value = value;
+        let mut schema = Some(schema);
+        for (i, segment) in self.path.iter().enumerate() {
+            if !value.is_mapping() {
+                *value = Value::Mapping(Mapping::new());
+            }
+            let key = resolve(segment, value, schema);
+            schema = schema.and_then(|schema| schema.get(&key));
+            let mapping = value.as_mapping_mut().unwrap();
+            if i + 1 == self.path.len() {
+                mapping.insert(key, self.value.clone());
+                return;
+            }
+            if !mapping.contains_key(&key) {
+                mapping.insert(key.clone(), Value::Mapping(Mapping::new()));
+            }
+            value = mapping.get_mut(&key).unwrap();
+        }
+    }
+
+    /// Whether the setting exists in `schema`. Anything below a mapping
+    /// without keys, like `keys`, is accepted.
+    pub fn is_known(&self, schema: &Value) -> bool {
+        let mut schema = schema;
+        for segment in &self.path {
+            match schema.as_mapping() {
+                Some(mapping) if mapping.is_empty() => return true,
+                Some(_) => match schema.get(resolve(segment, schema, None)) {
+                    Some(inner) => schema = inner,
+                    None => return false,
+                },
+                None => return false,
+            }
+        }
+        true
+    }
+}
+
+fn parse_value(s: &str) -> Value {
+    serde_yaml::from_str(s).unwrap_or_else(|_| Value::String(s.to_owned()))
+}
+
+fn normalize(key: &str) -> String {
+    key.to_lowercase().replace('-', "_")
+}
+
+fn resolve(segment: &str, value: &Value, schema: Option<&Value>) -> Value {
+    let segment_key = normalize(segment);
+    let matches = |key: &&Value| {
+        key.as_str()
+            .is_some_and(|key| normalize(key) == segment_key)
+    };
+    value
+        .as_mapping()
+        .and_then(|mapping| mapping.keys().find(matches))
+        .or_else(|| {
+            schema
+                .and_then(Value::as_mapping)
+                .and_then(|mapping| mapping.keys().find(matches))
+        })
+        .cloned()
+        .unwrap_or_else(|| Value::String(segment.to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn yaml(text: &str) -> Value {
+        serde_yaml::from_str(text).unwrap()
+    }
+
+    #[test]
+    fn parses_set_arguments() {
+        let o = Override::parse_arg("general.comparison=Best Segments").unwrap();
+        assert_eq!(o.source, Source::CommandLine);
+        assert_eq!(o.path, ["general", "comparison"]);
+        assert_eq!(o.value, Value::String("Best Segments".into()));
+
+        let o = Override::parse_arg(" window.fps = 30").unwrap();
+        assert_eq!(o.path, ["window", "fps"]);
+        assert_eq!(o.value, yaml("30"));
+        let o = Override::parse_arg("general.use-global-hotkeys=true").unwrap();
+        assert_eq!(o.value, Value::Bool(true));
+        // Not valid YAML, so taken as it is.
+        let o = Override::parse_arg("general.comparison=a: b: c").unwrap();
+        assert_eq!(o.value, Value::String("a: b: c".into()));
+
+        assert!(Override::parse_arg("general.comparison").is_err());
+        assert!(Override::parse_arg("=x").is_err());
+        assert!(Override::parse_arg("general..comparison=x").is_err());
+    }
+
+    #[test]
+    fn reads_environment_variables() {
+        env::set_var("LIVESPLIT_ONE_GENERAL__AUTO_SPLITTER", "celeste.wasm");
+        env::set_var("LIVESPLIT_ONE_PROFILE", "wide");
+        let overrides = Override::from_env();
+        env::remove_var("LIVESPLIT_ONE_GENERAL__AUTO_SPLITTER");
+        env::remove_var("LIVESPLIT_ONE_PROFILE");
+
+        let o = overrides
+            .iter()
+            .find(|o| o.path == ["general", "auto_splitter"])
+            .unwrap();
+        assert_eq!(o.source, Source::Environment);
+        assert_eq!(o.value, Value::String("celeste.wasm".into()));
+        assert!(!overrides.iter().any(|o| o.path == ["profile"]));
+    }
+
+    #[test]
+    fn applies_to_the_matching_setting() {
+        let schema = yaml("general:\n  auto-splitter: ~\n  comparison: ~\n");
+        let mut value = yaml("general:\n  comparison: Best Segments\n");
+        let env = Override::new(
+            Source::Environment,
+            "general.auto_splitter",
+            "a.wasm".into(),
+        );
+        env.apply(&mut value, &schema);
+        let arg = Override::parse_arg("GENERAL.Comparison=Average Segments").unwrap();
+        arg.apply(&mut value, &schema);
+        let expected = yaml("general:\n  comparison: Average Segments\n  auto-splitter: a.wasm\n");
+        assert_eq!(value, expected);
+    }
+
+    #[test]
+    fn later_overrides_win() {
+        let schema = yaml("general:\n  comparison: ~\n");
+        let mut value = yaml("general:\n  comparison: From the file\n");
+        let env = Override::new(Source::Environment, "general.comparison", "env".into());
+        let arg = Override::parse_arg("general.comparison=arg").unwrap();
+        for o in [env, arg] {
+            o.apply(&mut value, &schema);
+        }
+        assert_eq!(value, yaml("general:\n  comparison: arg\n"));
+    }
+}
@@ -0,0 +1,148 @@
+use crate::defaults::KEEP_BACKUPS;
+use crate::persist::Backups;
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Where the files written by the app go. Every location defaults to the
+/// platform's directory for it and can be moved in the `paths` section.
+#[derive(Default, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+#[serde(default)]
+pub struct Paths {
+    state: Option<PathBuf>,
+    logs: Option<PathBuf>,
+    backups: Option<PathBuf>,
+    cache: Option<PathBuf>,
+    keep_backups: Option<usize>,
+}
+
+fn project_dirs() -> Option<ProjectDirs> {
+    ProjectDirs::from("org", "LiveSplit", "LiveSplit One")
+}
+
+impl Paths {
+    /// Every location filled in with its default.
+    pub fn defaults() -> Self {
+        let none = Self::default();
+        Self {
+            state: Some(none.state_dir()),
+            logs: Some(none.log_dir()),
+            backups: Some(none.backup_dir()),
+            cache: Some(none.cache_dir()),
+            keep_backups: Some(KEEP_BACKUPS),
+        }
+    }
+    /// Timer state files. Only Linux has a state directory, elsewhere the
+    /// local data directory is used.
+    pub fn state_dir(&self) -> PathBuf {
+        self.state.clone().unwrap_or_else(|| {
+            project_dirs().map_or_else(
+                || ".".into(),
+                |dirs| {
+                    dirs.state_dir()
+                        .unwrap_or_else(|| dirs.data_local_dir())
+                        .to_path_buf()
+                },
+            )
+        })
+    }
+    pub fn log_dir(&self) -> PathBuf {
+        self.logs
+            .clone()
+            .unwrap_or_else(|| self.state_dir().join("logs"))
+    }
+    pub fn backup_dir(&self) -> PathBuf {
+        self.backups.clone().unwrap_or_else(|| {
+            project_dirs().map_or_else(|| "backups".into(), |dirs| dirs.data_dir().join("backups"))
+        })
+    }
+    pub fn backups(&self) -> Backups {
+        Backups {
+            dir: self.backup_dir(),
+            keep: self.keep_backups.unwrap_or(KEEP_BACKUPS),
+        }
+    }
+    pub fn cache_dir(&self) -> PathBuf {
+        self.cache.clone().unwrap_or_else(|| {
+            project_dirs().map_or_else(|| "cache".into(), |dirs| dirs.cache_dir().to_path_buf())
+        })
+    }
+
+    /// Creates every directory, logging where they are.
+    pub fn create_all(&self) {
+        let dirs = [
+            ("state", self.state_dir()),
+            ("logs", self.log_dir()),
+            ("backups", self.backup_dir()),
+            ("cache", self.cache_dir()),
+        ];
+        for (name, dir) in dirs {
+            match fs::create_dir_all(&dir) {
+                Ok(()) => log::info!("Using {} for {}", dir.display(), name),
+                Err(e) => log::error!("Couldn't create {}: {}", dir.display(), e),
+            }
+        }
+    }
+}
+
+/// Moves a file written by an older build to where it belongs now. Does
+/// nothing once the old file is gone.
+pub fn adopt(old: &Path, new: &Path) {
+    if old == new || !old.is_file() {
+        return;
+    }
+    if new.exists() {
+        log::error!(
+            "Not moving {} to {}, there is a file there already",
+            old.display(),
+            new.display()
+        );
+        return;
+    }
+    match move_file(old, new) {
+        Ok(()) => log::info!("Moved {} to {}", old.display(), new.display()),
+        Err(e) => log::error!(
+            "Couldn't move {} to {}: {}",
+            old.display(),
+            new.display(),
+            e
+        ),
+    }
+}
+
+fn move_file(old: &Path, new: &Path) -> io::Result<()> {
+    if let Some(dir) = new.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    // Renaming fails across file systems, the data dirs are often on another
+    // one than the splits.
+    if fs::rename(old, new).is_err() {
+        fs::copy(old, new)?;
+        fs::remove_file(old)?;
+    }
+    Ok(())
+}
+
+/// A file name for `path` that no other file shares, its stem followed by a
+/// hash of its canonical path. The hash is FNV-1a, which unlike the standard
+/// library's hasher gives the same name in every build.
+pub fn unique_name(path: &Path) -> PathBuf {
+    let full = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let hash = full
+        .to_string_lossy()
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        });
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let mut name = format!("{}-{:016x}", stem, hash);
+    if let Some(extension) = path.extension() {
+        name.push('.');
+        name.push_str(&extension.to_string_lossy());
+    }
+    PathBuf::from(name)
+}
@@ -0,0 +1,192 @@
+use crate::{paths, send_message};
+use lazy_static::lazy_static;
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{channel, Sender},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+/// How long it has to be quiet before the pending writes happen. Everything
+/// saved during a burst, like scrolling through comparisons, is written once.
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Where the previous versions of a file are kept, `<name>.1` being the
+/// newest. The name is unique to the file's path, see `paths::unique_name`.
+#[derive(Clone)]
+pub struct Backups {
+    pub dir: PathBuf,
+    pub keep: usize,
+}
+
+struct Pending {
+    data: Vec<u8>,
+    backups: Option<Backups>,
+}
+
+lazy_static! {
+    /// The latest data for every file that hasn't been written yet. An entry
+    /// stays until its data is on disk, so reads never miss it.
+    static ref PENDING: Mutex<BTreeMap<PathBuf, Arc<Pending>>> = Mutex::new(BTreeMap::new());
+    /// Held while writing, so writes to the same file never overtake each
+    /// other.
+    static ref WRITING: Mutex<()> = Mutex::new(());
+    static ref WAKE: Mutex<Sender<()>> = Mutex::new(spawn_writer());
+}
+
+fn spawn_writer() -> Sender<()> {
+    let (tx, rx) = channel();
+    thread::Builder::new()
+        .name("persistence".into())
+        .spawn(move || {
+            while rx.recv().is_ok() {
+                while rx.recv_timeout(DEBOUNCE).is_ok() {}
+                flush();
+            }
+        })
+        .unwrap();
+    tx
+}
+
+/// Saves `data` to `path` in the background, once the burst of saves it is
+/// part of is over. Errors are reported, not returned. The file is rotated
+/// into `backups` if any save of the burst asked for it.
+pub fn save(path: &Path, data: impl Into<Vec<u8>>, backups: Option<Backups>) {
+    let mut pending = PENDING.lock().unwrap();
+    let backups = backups.or_else(|| pending.get(path).and_then(|p| p.backups.clone()));
+    let data = data.into();
+    pending.insert(path.to_path_buf(), Arc::new(Pending { data, backups }));
+    drop(pending);
+    let _ = WAKE.lock().unwrap().send(());
+}
+
+/// Reads `path`, including any changes that are still waiting to be written.
+/// It doesn't wait for writes, the file is replaced by renaming so it is
+/// either the old or the new one.
+pub fn read(path: &Path) -> io::Result<Vec<u8>> {
+    if let Some(pending) = PENDING.lock().unwrap().get(path) {
+        return Ok(pending.data.clone());
+    }
+    fs::read(path)
+}
+
+/// Writes everything that is pending right away. Called before exiting.
+pub fn flush() {
+    let _writing = WRITING.lock().unwrap();
+    let pending = PENDING.lock().unwrap().clone();
+    for (path, pending) in pending {
+        if let Err(e) = write(&path, &pending.data, pending.backups.as_ref()) {
+            log::error!("Couldn't save {}: {}", path.display(), e);
+            send_message(&format!("Couldn't save {}: {}", path.display(), e));
+        }
+        // Saved again while being written, that is written next time.
+        let mut left = PENDING.lock().unwrap();
+        if left.get(&path).map_or(false, |p| Arc::ptr_eq(p, &pending)) {
+            left.remove(&path);
+        }
+    }
+}
+
+/// Replaces the file at `path` without ever leaving a partly written one
+/// behind: the data goes to a temporary file that is renamed over it. The old
+/// file is rotated into the backups first.
+pub fn write(path: &Path, data: &[u8], backups: Option<&Backups>) -> io::Result<()> {
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)?;
+    }
+    if let Some(backups) = backups {
+        if path.is_file() {
+            rotate(path, backups)?;
+        }
+    }
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".tmp");
+    let tmp = path.with_file_name(name);
+    let result = fs::File::create(&tmp).and_then(|mut file| {
+        io::Write::write_all(&mut file, data)?;
+        file.sync_all()
+    });
+    result.and_then(|_| fs::rename(&tmp, path)).map_err(|e| {
+        let _ = fs::remove_file(&tmp);
+        e
+    })
+}
+
+fn rotate(path: &Path, backups: &Backups) -> io::Result<()> {
+    if backups.keep == 0 {
+        return Ok(());
+    }
+    fs::create_dir_all(&backups.dir)?;
+    let name = paths::unique_name(path);
+    let name = name.to_string_lossy();
+    let backup = |n: usize| backups.dir.join(format!("{}.{}", name, n));
+    let _ = fs::remove_file(backup(backups.keep));
+    for n in (1..backups.keep).rev() {
+        if backup(n).is_file() {
+            fs::rename(backup(n), backup(n + 1))?;
+        }
+    }
+    fs::copy(path, backup(1))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn backups(name: &str, keep: usize) -> (PathBuf, Backups) {
+        let dir = std::env::temp_dir().join(format!("livesplit-one-{}-test", name));
+        let _ = fs::remove_dir_all(&dir);
+        let backups = Backups {
+            dir: dir.join("backups"),
+            keep,
+        };
+        (dir.join("run.lss"), backups)
+    }
+
+    fn backup(path: &Path, backups: &Backups, n: usize) -> Option<String> {
+        let name = paths::unique_name(path);
+        let backup = backups
+            .dir
+            .join(format!("{}.{}", name.to_string_lossy(), n));
+        fs::read_to_string(backup).ok()
+    }
+
+    #[test]
+    fn rotates_the_old_versions_newest_first() {
+        let (path, backups) = backups("rotate", 3);
+        for data in ["first", "second", "third"] {
+            write(&path, data.as_bytes(), Some(&backups)).unwrap();
+        }
+        assert_eq!(fs::read_to_string(&path).unwrap(), "third");
+        assert_eq!(backup(&path, &backups, 1).as_deref(), Some("second"));
+        assert_eq!(backup(&path, &backups, 2).as_deref(), Some("first"));
+        assert_eq!(backup(&path, &backups, 3), None);
+    }
+
+    #[test]
+    fn keeps_only_as_many_backups_as_asked() {
+        let (path, backups) = backups("keep", 2);
+        for data in ["first", "second", "third", "fourth"] {
+            write(&path, data.as_bytes(), Some(&backups)).unwrap();
+        }
+        assert_eq!(backup(&path, &backups, 1).as_deref(), Some("third"));
+        assert_eq!(backup(&path, &backups, 2).as_deref(), Some("second"));
+        assert_eq!(backup(&path, &backups, 3), None);
+        assert_eq!(fs::read_dir(&backups.dir).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn no_backups_are_made_when_none_are_kept() {
+        let (path, backups) = backups("keep-none", 0);
+        write(&path, b"first", Some(&backups)).unwrap();
+        write(&path, b"second", Some(&backups)).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second");
+        assert!(!backups.dir.exists());
+    }
+}
@@ -0,0 +1,85 @@
+use crate::persist;
+use livesplit_core::TimingMethod;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Settings that belong to a single splits file. They are stored next to it,
+/// in `<splits file>.yaml`, and take precedence over the ones in `general`.
+#[derive(Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SplitsSettings {
+    #[serde(skip)]
+    path: PathBuf,
+    /// The directory of the splits file, which relative paths are relative
+    /// to.
+    #[serde(skip)]
+    dir: PathBuf,
+    /// Like `Config::locked`, for a settings file next to the splits that
+    /// is broken. Picking a layout or comparison then lasts until the app is
+    /// closed.
+    #[serde(skip)]
+    locked: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    layout: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comparison: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timing_method: Option<TimingMethod>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    auto_splitter: Option<PathBuf>,
+}
+
+impl SplitsSettings {
+    /// Reads the settings of the splits file, or the defaults if there are
+    /// none or they are broken.
+    pub fn load(splits: &Path) -> Self {
+        let path = sidecar_path(splits);
+        let mut settings = match fs::read(&path) {
+            Ok(buf) => serde_yaml::from_slice(&buf).unwrap_or_else(|e| {
+                log::error!("Couldn't parse {}: {}", path.display(), e);
+                Self {
+                    locked: true,
+                    ..Default::default()
+                }
+            }),
+            Err(_) => Self::default(),
+        };
+        settings.dir = splits.parent().map(Path::to_path_buf).unwrap_or_default();
+        settings.path = path;
+        settings
+    }
+
+    /// The layout, relative to the splits file unless the path is absolute.
+    pub fn layout(&self) -> Option<PathBuf> {
+        self.layout.as_ref().map(|file| self.dir.join(file))
+    }
+    pub fn set_layout(&mut self, path: &Path) {
+        self.layout = Some(path.to_path_buf());
+    }
+    pub fn auto_splitter(&self) -> Option<PathBuf> {
+        self.auto_splitter.as_ref().map(|file| self.dir.join(file))
+    }
+
+    /// Writes the settings next to the splits file, with the paths as they
+    /// were read.
+    pub fn save(&self) {
+        if self.locked {
+            log::warn!(
+                "Not saving the splits settings, {} failed to parse",
+                self.path.display()
+            );
+            return;
+        }
+        let data = serde_yaml::to_string(self).unwrap();
+        persist::save(&self.path, data, None);
+    }
+}
+
+fn sidecar_path(splits: &Path) -> PathBuf {
+    let mut name = splits.file_name().unwrap_or_default().to_os_string();
+    name.push(".yaml");
+    splits.with_file_name(name)
+}
@@ -1,53 +1,118 @@
-use livesplit_core::{Timer, TimerPhase};
-use tokio::runtime::Runtime;
-use twitch_stream_markers::Client as MarkerClient;
+mod file;
+mod twitch;
+mod webhook;
+
+pub use self::{file::File, twitch::Twitch, webhook::Webhook};
+
+use crate::{
+    events::{self, Subscription, TimerEvent},
+    send_message,
+};
+use livesplit_core::TimerPhase;
+use std::{fmt, time::SystemTime};
+use tokio::runtime::{Handle, Runtime};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Status {
+    Connecting,
+    Ready,
+    Failed,
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Status::Connecting => "connecting",
+            Status::Ready => "ready",
+            Status::Failed => "failed",
+        })
+    }
+}
+
+/// A destination for stream markers. The decision of when to create a marker
+/// is made by the [`Client`], backends only deliver them.
+pub trait Backend: Send {
+    fn name(&self) -> &str;
+    fn status(&self) -> Status;
+    /// Creates a marker for the timer change at `time`. This must not block,
+    /// any network or disk access should happen on the runtime.
+    fn create_marker(&mut self, description: &str, time: SystemTime);
+}
 
 pub struct Client {
-    client: Option<MarkerClient>,
+    backends: Vec<Box<dyn Backend>>,
     is_running: Option<String>,
     runtime: Runtime,
 }
 
 impl Client {
-    pub fn new(token: Option<&str>) -> Self {
-        if let Some(token) = token {
-            if let Ok(rt) = Runtime::new() {
-                return Self {
-                    client: rt.block_on(MarkerClient::new(token)).ok(),
-                    is_running: None,
-                    runtime: rt,
-                };
+    /// Fails if the runtime can't be started, which disables the markers.
+    pub fn new() -> Option<Self> {
+        match Runtime::new() {
+            Ok(runtime) => Some(Self {
+                backends: Vec::new(),
+                is_running: None,
+                runtime,
+            }),
+            Err(e) => {
+                let e = anyhow::Error::new(e).context("Couldn't start the runtime");
+                report_error("Stream markers", &e);
+                None
             }
         }
-        Self {
-            client: None,
-            is_running: None,
-            runtime: Runtime::new().unwrap(),
+    }
+
+    /// The handle backends should spawn their work on.
+    pub fn handle(&self) -> Handle {
+        self.runtime.handle().clone()
+    }
+
+    pub fn add(&mut self, backend: impl Backend + 'static) {
+        self.backends.push(Box::new(backend));
+    }
+
+    /// Creates markers from the timer events on a thread of their own, until
+    /// the subscription is dropped.
+    pub fn subscribe(mut self) -> Subscription {
+        events::subscribe("markers", move |event| self.on_event(event))
+    }
+
+    fn on_event(&mut self, event: &TimerEvent) {
+        if self.backends.is_empty() {
+            return;
+        }
+        let is_running = event.phase != TimerPhase::NotRunning;
+        if !is_running {
+            if let Some(description) = self.is_running.take() {
+                self.create_marker(&format!("End of {}", description), event.time);
+            }
+        } else if self.is_running.is_none() {
+            let description = format!("attempt {} in {}", event.attempt, event.run_name);
+            self.create_marker(&format!("Start of {}", description), event.time);
+            self.is_running = Some(description);
         }
     }
 
-    pub fn tick(&mut self, timer: &Timer) {
-        if let Some(client) = &self.client {
-            let is_running = timer.current_phase() != TimerPhase::NotRunning;
-            if !is_running {
-                if let Some(description) = self.is_running.take() {
-                    let marker = client.create_marker(Some(&format!("End of {}", description)));
-                    self.runtime.spawn(async move {
-                        let _ = marker.await;
-                    });
-                }
-            } else if self.is_running.is_none() {
-                let description = format!(
-                    "attempt {} in {}",
-                    timer.run().attempt_count(),
-                    timer.run().extended_name(false)
-                );
-                let marker = client.create_marker(Some(&format!("Start of {}", description)));
-                self.runtime.spawn(async {
-                    let _ = marker.await;
-                });
-                self.is_running = Some(description);
+    fn create_marker(&mut self, description: &str, time: SystemTime) {
+        for backend in &mut self.backends {
+            match backend.status() {
+                Status::Connecting | Status::Ready => backend.create_marker(description, time),
+                Status::Failed => log::warn!(
+                    "{}: not connected, dropping marker \"{}\"",
+                    backend.name(),
+                    description
+                ),
             }
         }
     }
 }
+
+fn report_status(backend: &str, status: Status) {
+    log::info!("{}: {}", backend, status);
+    send_message(&format!("{}: {}", backend, status));
+}
+
+fn report_error(backend: &str, e: &anyhow::Error) {
+    log::error!("{}: {:#}", backend, e);
+    send_message(&format!("{}: {:#}", backend, e));
+}
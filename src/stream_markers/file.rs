@@ -0,0 +1,105 @@
+use super::{report_error, Backend, Status};
+use anyhow::Context;
+use lazy_static::lazy_static;
+use std::{
+    collections::HashSet,
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{channel, Sender},
+        Mutex,
+    },
+    time::SystemTime,
+};
+use tokio::runtime::Handle;
+
+const NAME: &str = "Marker file";
+
+lazy_static! {
+    /// When the first marker file was opened. Reloading the splits builds new
+    /// backends, but the times keep counting from here.
+    static ref SESSION_START: SystemTime = SystemTime::now();
+    /// The marker files the start of the session has been written to.
+    static ref STARTED: Mutex<HashSet<PathBuf>> = Mutex::new(HashSet::new());
+}
+
+/// Appends markers to a text file as chapters, with the time since the
+/// session started in front of each description.
+pub struct File {
+    /// The lines to append, written in order on the runtime.
+    lines: Option<Sender<String>>,
+}
+
+impl File {
+    pub fn new(handle: &Handle, path: &Path) -> Self {
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut file| {
+                if STARTED.lock().unwrap().insert(path.to_path_buf()) {
+                    writeln!(
+                        file,
+                        "# Session started {}",
+                        humantime::format_rfc3339_seconds(*SESSION_START)
+                    )?;
+                }
+                Ok(file)
+            })
+            .with_context(|| format!("Couldn't open {}", path.display()));
+        let lines = match file {
+            Ok(file) => Some(spawn_writer(handle, file, path.to_path_buf())),
+            Err(e) => {
+                report_error(NAME, &e);
+                None
+            }
+        };
+        Self { lines }
+    }
+}
+
+/// Writes the lines sent to it until the sender is dropped.
+fn spawn_writer(handle: &Handle, mut file: fs::File, path: PathBuf) -> Sender<String> {
+    let (tx, rx) = channel::<String>();
+    handle.spawn_blocking(move || {
+        for line in rx {
+            if let Err(e) = writeln!(file, "{}", line) {
+                let e =
+                    anyhow::Error::new(e).context(format!("Couldn't write to {}", path.display()));
+                report_error(NAME, &e);
+            }
+        }
+    });
+    tx
+}
+
+impl Backend for File {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn status(&self) -> Status {
+        if self.lines.is_some() {
+            Status::Ready
+        } else {
+            Status::Failed
+        }
+    }
+
+    fn create_marker(&mut self, description: &str, time: SystemTime) {
+        if let Some(lines) = &self.lines {
+            let secs = time
+                .duration_since(*SESSION_START)
+                .unwrap_or_default()
+                .as_secs();
+            let _ = lines.send(format!(
+                "{:02}:{:02}:{:02} {}",
+                secs / 3600,
+                secs / 60 % 60,
+                secs % 60,
+                description
+            ));
+        }
+    }
+}
@@ -0,0 +1,161 @@
+use super::{report_error, report_status, Backend, Status};
+use crate::send_message;
+use anyhow::anyhow;
+use lazy_static::lazy_static;
+use std::{
+    future::Future,
+    mem, panic,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+use tokio::runtime::Handle;
+use twitch_stream_markers::{Client as MarkerClient, Scheduler};
+
+const NAME: &str = "Twitch";
+
+lazy_static! {
+    /// Shared by every connection, so reloading the splits doesn't forget how
+    /// much of the rate limit is left. Setting up HTTPS panics if the system
+    /// has no root certificates, which leaves this empty.
+    static ref SCHEDULER: Option<Arc<Scheduler>> =
+        panic::catch_unwind(Scheduler::new).ok().map(Arc::new);
+}
+
+/// Tokens that expire sooner than this get a warning at startup.
+const EXPIRY_WARNING: Duration = Duration::from_secs(24 * 60 * 60);
+
+enum Connection {
+    /// Markers created before the connection is established are queued up
+    /// and sent once it is ready.
+    Connecting(Vec<String>),
+    Ready(Box<MarkerClient>),
+    Failed,
+}
+
+pub struct Twitch {
+    connection: Arc<Mutex<Connection>>,
+    handle: Handle,
+}
+
+impl Twitch {
+    pub fn new(handle: &Handle, token: &str) -> Self {
+        let connection = Arc::new(Mutex::new(Connection::Connecting(Vec::new())));
+        let scheduler = match &*SCHEDULER {
+            Some(scheduler) => scheduler.clone(),
+            None => {
+                fail(
+                    &connection,
+                    &anyhow!("Couldn't set up HTTPS, the system may have no root certificates"),
+                );
+                return Self {
+                    connection,
+                    handle: handle.clone(),
+                };
+            }
+        };
+        report_status(NAME, Status::Connecting);
+        let task = handle.spawn(connect(connection.clone(), scheduler, token.to_owned()));
+        // A panic while connecting would leave the markers queueing up.
+        let failed = connection.clone();
+        handle.spawn(async move {
+            if let Err(e) = task.await {
+                fail(&failed, &anyhow!("Connecting failed: {}", e));
+            }
+        });
+        Self {
+            connection,
+            handle: handle.clone(),
+        }
+    }
+}
+
+impl Backend for Twitch {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn status(&self) -> Status {
+        match &*self.connection.lock().unwrap() {
+            Connection::Connecting(_) => Status::Connecting,
+            Connection::Ready(_) => Status::Ready,
+            Connection::Failed => Status::Failed,
+        }
+    }
+
+    /// Twitch puts the marker where the stream is when it gets the request.
+    fn create_marker(&mut self, description: &str, _time: SystemTime) {
+        match &mut *self.connection.lock().unwrap() {
+            Connection::Ready(client) => {
+                self.handle.spawn(send_marker(client, description));
+            }
+            Connection::Connecting(queue) => queue.push(description.to_owned()),
+            Connection::Failed => {}
+        }
+    }
+}
+
+async fn connect(connection: Arc<Mutex<Connection>>, scheduler: Arc<Scheduler>, token: String) {
+    match MarkerClient::with_scheduler(&token, scheduler).await {
+        Ok(client) => {
+            report_expiry(&client);
+            let queue = {
+                let mut connection = connection.lock().unwrap();
+                let queue = match &mut *connection {
+                    Connection::Connecting(queue) => mem::take(queue),
+                    _ => Vec::new(),
+                };
+                let markers: Vec<_> = queue
+                    .iter()
+                    .map(|description| send_marker(&client, description))
+                    .collect();
+                *connection = Connection::Ready(Box::new(client));
+                markers
+            };
+            report_status(NAME, Status::Ready);
+            for marker in queue {
+                marker.await;
+            }
+        }
+        Err(e) => fail(&connection, &e),
+    }
+}
+
+/// Drops the queued markers and reports why.
+fn fail(connection: &Mutex<Connection>, e: &anyhow::Error) {
+    *connection.lock().unwrap() = Connection::Failed;
+    report_error(NAME, e);
+    report_status(NAME, Status::Failed);
+}
+
+fn send_marker(
+    client: &MarkerClient,
+    description: &str,
+) -> impl Future<Output = ()> + Send + 'static {
+    let marker = client.create_marker(Some(description));
+    async move {
+        if let Err(e) = marker.await {
+            report_error(NAME, &e);
+        }
+    }
+}
+
+fn report_expiry(client: &MarkerClient) {
+    let validation = client.validation();
+    match validation.expires_in() {
+        Some(expires_in) if expires_in < EXPIRY_WARNING => {
+            let message = format!(
+                "Twitch token for {} expires in {}, generate a new one soon",
+                validation.login,
+                humantime::format_duration(expires_in)
+            );
+            log::warn!("{}", message);
+            send_message(&message);
+        }
+        Some(expires_in) => log::info!(
+            "Twitch token for {} expires in {}",
+            validation.login,
+            humantime::format_duration(expires_in)
+        ),
+        None => log::info!("Twitch token for {} does not expire", validation.login),
+    }
+}
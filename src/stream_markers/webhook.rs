@@ -0,0 +1,75 @@
+use super::{report_error, Backend, Status};
+use hyper::{client::HttpConnector, header::CONTENT_TYPE, Body, Request};
+use hyper_rustls::HttpsConnector;
+use serde::Serialize;
+use std::time::SystemTime;
+use tokio::runtime::Handle;
+
+const NAME: &str = "Webhook";
+
+#[derive(Serialize)]
+struct Payload<'a> {
+    description: &'a str,
+    timestamp: String,
+}
+
+/// POSTs every marker as JSON to a URL.
+pub struct Webhook {
+    client: hyper::Client<HttpsConnector<HttpConnector>>,
+    url: String,
+    handle: Handle,
+}
+
+impl Webhook {
+    pub fn new(handle: &Handle, url: &str) -> Self {
+        Self {
+            client: hyper::Client::builder().build(HttpsConnector::with_native_roots()),
+            url: url.to_owned(),
+            handle: handle.clone(),
+        }
+    }
+}
+
+impl Backend for Webhook {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn status(&self) -> Status {
+        Status::Ready
+    }
+
+    fn create_marker(&mut self, description: &str, time: SystemTime) {
+        let body = serde_json::to_vec(&Payload {
+            description,
+            timestamp: humantime::format_rfc3339_seconds(time).to_string(),
+        })
+        .unwrap();
+        let request = match Request::post(self.url.as_str())
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(body))
+        {
+            Ok(request) => request,
+            Err(e) => {
+                report_error(NAME, &e.into());
+                return;
+            }
+        };
+        let response = self.client.request(request);
+        let url = self.url.clone();
+        self.handle.spawn(async move {
+            let result = match response.await {
+                Ok(response) if response.status().is_success() => Ok(()),
+                Ok(response) => Err(anyhow::anyhow!(
+                    "{} responded with {}",
+                    url,
+                    response.status()
+                )),
+                Err(e) => Err(anyhow::Error::new(e).context(format!("Couldn't reach {}", url))),
+            };
+            if let Err(e) = result {
+                report_error(NAME, &e);
+            }
+        });
+    }
+}
@@ -1,13 +1,20 @@
-use anyhow::Context;
+mod scheduler;
+
+pub use scheduler::Scheduler;
+
+use anyhow::{bail, Context};
 use hyper::{
     body::{aggregate, Buf},
-    client::HttpConnector,
-    header::AUTHORIZATION,
-    Body, Request,
+    header::{AUTHORIZATION, CONTENT_TYPE},
+    Request, StatusCode,
 };
-use hyper_rustls::HttpsConnector;
 use serde::{Deserialize, Serialize};
 use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// The scope a token needs in order to create stream markers.
+pub const MARKER_SCOPE: &str = "channel:manage:broadcast";
 
 #[derive(Serialize)]
 struct CreateMarker<'a> {
@@ -20,9 +27,32 @@ struct Response<T> {
     data: Vec<T>,
 }
 
+/// The information Twitch reports about a token on the OAuth validate
+/// endpoint.
 #[derive(Debug, Deserialize)]
-struct User {
-    id: String,
+pub struct Validation {
+    pub client_id: String,
+    pub login: String,
+    pub user_id: String,
+    #[serde(default)]
+    pub scopes: Vec<String>,
+    /// Seconds until the token expires. Twitch reports `0` for tokens that
+    /// don't expire.
+    pub expires_in: u64,
+}
+
+impl Validation {
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes.iter().any(|s| s == scope)
+    }
+
+    pub fn expires_in(&self) -> Option<Duration> {
+        if self.expires_in == 0 {
+            None
+        } else {
+            Some(Duration::from_secs(self.expires_in))
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -34,68 +64,96 @@ pub struct Marker {
 }
 
 pub struct Client {
-    client: hyper::Client<HttpsConnector<HttpConnector>>,
-    user_id: String,
+    scheduler: Arc<Scheduler>,
+    validation: Validation,
     auth: String,
 }
 
 impl Client {
     pub async fn new(token: &str) -> anyhow::Result<Self> {
-        let auth = format!("Bearer {}", token);
-        let https = HttpsConnector::with_native_roots();
-        let client = hyper::Client::builder().build(https);
-
-        let response = client
-            .request(
-                Request::get("https://api.twitch.tv/helix/users")
-                    .header(AUTHORIZATION, auth.as_str())
-                    .body(Body::empty())
-                    .unwrap(),
-            )
-            .await?;
+        Self::with_scheduler(token, Arc::new(Scheduler::new())).await
+    }
 
-        let bytes = aggregate(response.into_body()).await?;
-        let users: Response<User> = serde_json::from_reader(bytes.reader())?;
+    /// Creates a client that sends its requests through `scheduler`, sharing
+    /// the rate limit with the other clients using it.
+    pub async fn with_scheduler(token: &str, scheduler: Arc<Scheduler>) -> anyhow::Result<Self> {
+        let validation = validate(&scheduler, token).await?;
+        if !validation.has_scope(MARKER_SCOPE) {
+            bail!(
+                "The Twitch token for {} is missing the `{}` scope, which is required to create stream markers. Generate a new token with that scope.",
+                validation.login,
+                MARKER_SCOPE
+            );
+        }
 
         Ok(Self {
-            client,
-            user_id: users
-                .data
-                .into_iter()
-                .next()
-                .context("Twitch didn't respond with a User ID.")?
-                .id,
-            auth,
+            scheduler,
+            validation,
+            auth: format!("Bearer {}", token),
         })
     }
 
+    /// What Twitch reported about the token when the client was created.
+    pub fn validation(&self) -> &Validation {
+        &self.validation
+    }
+
     pub fn create_marker(
         &self,
         description: Option<&str>,
     ) -> impl Future<Output = anyhow::Result<Marker>> {
-        let request = self.client.request(
-            Request::post("https://api.twitch.tv/helix/streams/markers")
-                .header(AUTHORIZATION, self.auth.as_str())
-                .body(
-                    serde_json::to_vec(&CreateMarker {
-                        user_id: &self.user_id,
-                        description,
-                    })
-                    .unwrap()
-                    .into(),
-                )
+        let scheduler = self.scheduler.clone();
+        let request = Request::post("https://api.twitch.tv/helix/streams/markers")
+            .header(AUTHORIZATION, self.auth.as_str())
+            .header("Client-Id", self.validation.client_id.as_str())
+            .header(CONTENT_TYPE, "application/json")
+            .body(
+                serde_json::to_vec(&CreateMarker {
+                    user_id: &self.validation.user_id,
+                    description,
+                })
                 .unwrap(),
-        );
+            )
+            .unwrap();
 
         async move {
-            let bytes = aggregate(request.await?.into_body()).await?;
+            let response = scheduler.send(request).await?;
+            if response.status() == StatusCode::UNAUTHORIZED {
+                bail!("The Twitch token is invalid or has expired. Generate a new token.");
+            }
+            if !response.status().is_success() {
+                bail!("Twitch failed to create the marker: {}", response.status());
+            }
+            let bytes = aggregate(response.into_body()).await?;
             let markers: Response<Marker> = serde_json::from_reader(bytes.reader())?;
 
-            Ok(markers
+            markers
                 .data
                 .into_iter()
                 .next()
-                .context("Twitch didn't respond with a marker.")?)
+                .context("Twitch didn't respond with a marker.")
         }
     }
 }
+
+async fn validate(scheduler: &Scheduler, token: &str) -> anyhow::Result<Validation> {
+    let response = scheduler
+        .send(
+            Request::get("https://id.twitch.tv/oauth2/validate")
+                .header(AUTHORIZATION, format!("OAuth {}", token))
+                .body(Vec::new())
+                .unwrap(),
+        )
+        .await
+        .context("Couldn't reach Twitch to validate the token.")?;
+
+    if response.status() == StatusCode::UNAUTHORIZED {
+        bail!("The Twitch token is invalid or has expired. Generate a new token.");
+    }
+    if !response.status().is_success() {
+        bail!("Twitch failed to validate the token: {}", response.status());
+    }
+
+    let bytes = aggregate(response.into_body()).await?;
+    Ok(serde_json::from_reader(bytes.reader())?)
+}
@@ -0,0 +1,350 @@
+//! Sends requests to the Twitch API one at a time while honouring the
+//! `Ratelimit-Remaining` and `Ratelimit-Reset` headers, and retries requests
+//! that were rate limited or failed on Twitch's end. Requests that aren't
+//! idempotent, like creating a marker, are only retried after a network error
+//! if they can't have been sent.
+
+use hyper::{client::HttpConnector, Body, Request, Response, StatusCode};
+use hyper_rustls::HttpsConnector;
+use rand::Rng;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::{sync::Mutex, time::sleep};
+
+const RATELIMIT_REMAINING: &str = "ratelimit-remaining";
+const RATELIMIT_RESET: &str = "ratelimit-reset";
+
+#[derive(Default)]
+struct Budget {
+    remaining: Option<u64>,
+    /// When the bucket refills, in seconds since the Unix epoch.
+    reset: Option<u64>,
+}
+
+impl Budget {
+    fn update(&mut self, response: &Response<Body>) {
+        let header = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.parse().ok())
+        };
+        if let Some(remaining) = header(RATELIMIT_REMAINING) {
+            self.remaining = Some(remaining);
+        }
+        if let Some(reset) = header(RATELIMIT_RESET) {
+            self.reset = Some(reset);
+        }
+    }
+
+    /// How long to wait until the bucket refills, if it is empty.
+    fn wait(&self) -> Option<Duration> {
+        if self.remaining != Some(0) {
+            return None;
+        }
+        until(self.reset?)
+    }
+}
+
+fn until(unix_secs: u64) -> Option<Duration> {
+    UNIX_EPOCH
+        .checked_add(Duration::from_secs(unix_secs))?
+        .duration_since(SystemTime::now())
+        .ok()
+}
+
+pub struct Scheduler {
+    client: hyper::Client<HttpsConnector<HttpConnector>>,
+    budget: Mutex<Budget>,
+    max_retries: u32,
+    base_delay: Duration,
+}
+
+impl Default for Scheduler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Self::with_retries(3, Duration::from_millis(500))
+    }
+
+    /// Retries a failed request up to `max_retries` times. Server errors back
+    /// off exponentially starting at `base_delay`, and every wait has up to
+    /// `base_delay` of jitter added.
+    pub fn with_retries(max_retries: u32, base_delay: Duration) -> Self {
+        Self {
+            client: hyper::Client::builder().build(HttpsConnector::with_native_roots()),
+            budget: Mutex::new(Budget::default()),
+            max_retries,
+            base_delay,
+        }
+    }
+
+    /// Sends the request once the rate limit allows it. Requests are queued
+    /// and sent in order. The response of the last attempt is returned, even
+    /// if it is still an error.
+    pub async fn send(&self, request: Request<Vec<u8>>) -> hyper::Result<Response<Body>> {
+        let mut budget = self.budget.lock().await;
+        let mut attempt = 0;
+        loop {
+            if let Some(wait) = budget.wait() {
+                sleep(wait + self.jitter()).await;
+                budget.remaining = None;
+            }
+
+            let result = self.client.request(clone_request(&request)).await;
+            let retry = match &result {
+                Ok(response) => {
+                    budget.update(response);
+                    match response.status() {
+                        StatusCode::TOO_MANY_REQUESTS => {
+                            budget.remaining = Some(0);
+                            Some(budget.wait().unwrap_or(self.base_delay))
+                        }
+                        status if status.is_server_error() => Some(self.backoff(attempt)),
+                        _ => None,
+                    }
+                }
+                Err(e) if e.is_connect() || request.method().is_idempotent() => {
+                    Some(self.backoff(attempt))
+                }
+                Err(_) => None,
+            };
+
+            match retry {
+                Some(delay) if attempt < self.max_retries => {
+                    attempt += 1;
+                    sleep(delay + self.jitter()).await;
+                    budget.remaining = None;
+                }
+                _ => return result,
+            }
+        }
+    }
+
+    fn backoff(&self, attempt: u32) -> Duration {
+        self.base_delay * 2u32.saturating_pow(attempt)
+    }
+
+    fn jitter(&self) -> Duration {
+        self.base_delay.mul_f64(rand::thread_rng().gen())
+    }
+}
+
+fn clone_request(request: &Request<Vec<u8>>) -> Request<Body> {
+    let mut builder = Request::builder()
+        .method(request.method())
+        .uri(request.uri())
+        .version(request.version());
+    if let Some(headers) = builder.headers_mut() {
+        headers.extend(request.headers().clone());
+    }
+    builder.body(request.body().clone().into()).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hyper::{
+        service::{make_service_fn, service_fn},
+        Server,
+    };
+    use std::{
+        convert::Infallible,
+        io::Read,
+        net::{SocketAddr, TcpListener},
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Instant,
+    };
+
+    struct Reply {
+        status: StatusCode,
+        remaining: Option<u64>,
+        reset: Option<u64>,
+    }
+
+    fn reply(status: u16) -> Reply {
+        Reply {
+            status: StatusCode::from_u16(status).unwrap(),
+            remaining: None,
+            reset: None,
+        }
+    }
+
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+
+    /// Starts a server that answers with the scripted replies in order, and
+    /// repeats the last one after that. Returns its address and a counter of
+    /// the requests it received.
+    fn mock(replies: Vec<Reply>) -> (SocketAddr, Arc<AtomicUsize>) {
+        let replies = Arc::new(replies);
+        let count = Arc::new(AtomicUsize::new(0));
+        let counter = count.clone();
+        let make_service = make_service_fn(move |_| {
+            let replies = replies.clone();
+            let count = count.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |_| {
+                    let n = count.fetch_add(1, Ordering::SeqCst);
+                    let reply = &replies[n.min(replies.len() - 1)];
+                    let mut response = Response::builder().status(reply.status);
+                    if let Some(remaining) = reply.remaining {
+                        response = response.header(RATELIMIT_REMAINING, remaining);
+                    }
+                    if let Some(reset) = reply.reset {
+                        response = response.header(RATELIMIT_RESET, reset);
+                    }
+                    let response = response.body(Body::empty()).unwrap();
+                    async move { Ok::<_, Infallible>(response) }
+                }))
+            }
+        });
+        let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+        let addr = server.local_addr();
+        tokio::spawn(server);
+        (addr, counter)
+    }
+
+    /// Starts a server that hangs up on every request without answering.
+    /// Returns its address and a counter of the requests it received.
+    fn hang_up() -> (SocketAddr, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let count = Arc::new(AtomicUsize::new(0));
+        let counter = count.clone();
+        std::thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let _ = stream.read(&mut [0; 1024]);
+                count.fetch_add(1, Ordering::SeqCst);
+            }
+        });
+        (addr, counter)
+    }
+
+    fn get(addr: SocketAddr) -> Request<Vec<u8>> {
+        Request::get(format!("http://{}/", addr))
+            .body(Vec::new())
+            .unwrap()
+    }
+
+    fn post(addr: SocketAddr) -> Request<Vec<u8>> {
+        Request::post(format!("http://{}/", addr))
+            .body(b"{}".to_vec())
+            .unwrap()
+    }
+
+    fn scheduler() -> Scheduler {
+        Scheduler::with_retries(3, Duration::from_millis(10))
+    }
+
+    #[tokio::test]
+    async fn retries_server_errors() {
+        let (addr, count) = mock(vec![reply(500), reply(503), reply(200)]);
+        let response = scheduler().send(get(addr)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(count.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_retries() {
+        let (addr, count) = mock(vec![reply(500)]);
+        let response = scheduler().send(get(addr)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(count.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_client_errors() {
+        let (addr, count) = mock(vec![reply(401), reply(200)]);
+        let response = scheduler().send(get(addr)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn retries_gets_after_network_errors() {
+        let (addr, count) = hang_up();
+        assert!(scheduler().send(get(addr)).await.is_err());
+        assert_eq!(count.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn does_not_resend_posts_after_network_errors() {
+        let (addr, count) = hang_up();
+        assert!(scheduler().send(post(addr)).await.is_err());
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn retries_posts_that_could_not_connect() {
+        let addr = TcpListener::bind(("127.0.0.1", 0))
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let start = Instant::now();
+        let error = scheduler().send(post(addr)).await.unwrap_err();
+        assert!(error.is_connect());
+        assert!(start.elapsed() >= Duration::from_millis(70));
+    }
+
+    #[tokio::test]
+    async fn waits_for_reset_after_too_many_requests() {
+        let (addr, count) = mock(vec![
+            Reply {
+                remaining: Some(0),
+                reset: Some(now() + 2),
+                ..reply(429)
+            },
+            reply(200),
+        ]);
+        let start = Instant::now();
+        let response = scheduler().send(get(addr)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(count.load(Ordering::SeqCst), 2);
+        assert!(start.elapsed() >= Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn holds_requests_while_bucket_is_empty() {
+        let (addr, count) = mock(vec![
+            Reply {
+                remaining: Some(0),
+                reset: Some(now() + 2),
+                ..reply(200)
+            },
+            reply(200),
+        ]);
+        let scheduler = scheduler();
+        scheduler.send(get(addr)).await.unwrap();
+        let start = Instant::now();
+        scheduler.send(get(addr)).await.unwrap();
+        assert_eq!(count.load(Ordering::SeqCst), 2);
+        assert!(start.elapsed() >= Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn ignores_budget_when_remaining() {
+        let (addr, _) = mock(vec![Reply {
+            remaining: Some(5),
+            reset: Some(now() + 60),
+            ..reply(200)
+        }]);
+        let scheduler = scheduler();
+        let start = Instant::now();
+        for _ in 0..3 {
+            scheduler.send(get(addr)).await.unwrap();
+        }
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+}